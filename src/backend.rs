@@ -3,23 +3,162 @@
 use termion::color as termion_color;
 use termion::color::Color as TermColor;
 use termion::cursor::{Goto, Hide, Show};
-use termion::input::{MouseTerminal, TermRead};
+use termion::input::{MouseTerminal, TermReadEventsAndRaw};
 use termion::raw::{IntoRawMode, RawTerminal};
 use termion::screen::AlternateScreen;
+use termion::style as termion_style;
 
 use signal_hook::iterator::Signals;
 
-use std::fmt;
+use unicode_segmentation::UnicodeSegmentation;
+
+use std::fmt::{self, Write as FmtWrite};
 use std::io::{stdin, stdout, Stdout, Write};
-use std::iter::repeat;
 use std::thread;
 
-use {BackendContext, Color, Frame, Name, RenderBackend, Size};
+use input::InputDecoder;
+use ir::grapheme_width;
+use {
+    Attributes, BackendContext, Color, CursorShape, CursorStyle, Frame, Name, Pos, RenderBackend,
+    Size, Style,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+struct Cell {
+    symbol: String,
+    // Zero for the trailing half of a wide symbol
+    width: usize,
+    style: Style,
+    link: Option<String>,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell {
+            symbol: " ".to_owned(),
+            width: 1,
+            style: Style::default(),
+            link: None,
+        }
+    }
+}
+
+impl Cell {
+    fn continuation(style: Style, link: Option<String>) -> Self {
+        Cell {
+            symbol: String::new(),
+            width: 0,
+            style,
+            link,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct CellGrid {
+    size: Size,
+    rows: Vec<Vec<Cell>>,
+}
+
+impl CellGrid {
+    fn from_frame(frame: &Frame, size: Size) -> Self {
+        let mut rows: Vec<Vec<Cell>> = frame
+            .image
+            .iter()
+            .take(size.rows)
+            .map(|chunks| {
+                let mut row = Vec::with_capacity(size.cols);
+                for (style, text, _, link) in chunks {
+                    for grapheme in UnicodeSegmentation::graphemes(*text, true) {
+                        let width = grapheme_width(grapheme);
+                        if width == 0 {
+                            continue;
+                        }
+                        if row.len() + width > size.cols {
+                            break;
+                        }
+                        let symbol = grapheme.to_owned();
+                        let link = link.map(|l| l.to_owned());
+                        row.push(Cell {
+                            symbol,
+                            width,
+                            style: *style,
+                            link: link.clone(),
+                        });
+                        for _ in 1..width {
+                            row.push(Cell::continuation(*style, link.clone()));
+                        }
+                    }
+                }
+                row.resize(size.cols, Cell::default());
+                row
+            }).collect();
+        rows.resize(size.rows, vec![Cell::default(); size.cols]);
+        CellGrid { size, rows }
+    }
+
+    // Appends the escapes needed to turn `old` into `self`, touching only the changed cell runs.
+    // `pen` tracks the SGR state the terminal is currently in, if known.
+    // Hyperlinks are closed before every jump so they never bleed into unrelated cells.
+    fn diff(&self, old: Option<&CellGrid>, pen: &mut Option<Style>, out: &mut String) {
+        let old = old.filter(|o| o.size == self.size);
+        let mut cursor: Option<Pos> = None;
+        let mut open_link: Option<&str> = None;
+        for (row_idx, row) in self.rows.iter().enumerate() {
+            let mut col = 0;
+            while col < row.len() {
+                let cell = &row[col];
+                let span = if cell.width == 0 { 1 } else { cell.width };
+                let changed = match old {
+                    Some(old) => row[col..col + span] != old.rows[row_idx][col..col + span],
+                    None => true,
+                };
+                if changed {
+                    let here = Pos::new(col, row_idx);
+                    match cursor {
+                        Some(c) if c.col == here.col && c.row == here.row => {}
+                        _ => {
+                            if open_link.take().is_some() {
+                                push_link(out, None);
+                            }
+                            write!(out, "{}", Goto(col as u16 + 1, row_idx as u16 + 1)).unwrap()
+                        }
+                    }
+                    push_style_change(out, *pen, cell.style);
+                    *pen = Some(cell.style);
+                    let link = cell.link.as_deref();
+                    if link != open_link {
+                        push_link(out, link);
+                        open_link = link;
+                    }
+                    if cell.width == 0 {
+                        // Orphaned half of a wide symbol, blank it out
+                        out.push(' ');
+                    } else {
+                        out.push_str(&cell.symbol);
+                    }
+                    // Terminals defer wrapping at the right margin, so don't trust the position there
+                    cursor = if col + span < row.len() {
+                        Some(Pos::new(col + span, row_idx))
+                    } else {
+                        None
+                    };
+                }
+                col += span;
+            }
+        }
+        if open_link.is_some() {
+            push_link(out, None);
+        }
+    }
+}
 
 pub struct TermionBackend {
     screen: MouseTerminal<AlternateScreen<RawTerminal<Stdout>>>,
     pub size: Size,
-    last_frame: Vec<String>,
+    last_frame: Option<CellGrid>,
+    pen: Option<Style>,
+    cursor_style: CursorStyle,
 }
 
 impl RenderBackend for TermionBackend {
@@ -29,14 +168,17 @@ impl RenderBackend for TermionBackend {
         write!(screen, "{}", termion::clear::All).unwrap();
         let (width, height) = termion::terminal_size().unwrap();
         let size = Size::new(width as usize, height as usize);
-        let last_frame = repeat("".to_owned()).take(height as usize).collect();
+        let last_frame = None;
+        let pen = None;
         let ctx2 = ctx.clone();
         thread::spawn(move || {
+            let mut decoder = InputDecoder::new();
             /*let stdin = stdin();
             let mut events = stdin.events();*/
             'outer: loop {
-                for event in stdin().events() {
-                    match ctx.send_input(event.unwrap()) {
+                for event in stdin().events_and_raw() {
+                    let (event, raw) = event.unwrap();
+                    match ctx.send_input(decoder.decode(event, raw)) {
                         Ok(()) => continue,
                         Err(_) => break 'outer,
                     }
@@ -60,58 +202,123 @@ impl RenderBackend for TermionBackend {
             }
         });
 
+        let cursor_style = CursorStyle::Default;
         TermionBackend {
             size,
             screen,
             last_frame,
+            pen,
+            cursor_style,
         }
     }
     fn paint_frame(&mut self, frame: Frame) {
-        //write!(self.screen, "{}", termion::clear::All).unwrap();
-        let new_frame: Vec<String> = frame
-            .image
-            .into_iter()
-            .map(|chunks| {
-                chunks
-                    .into_iter()
-                    .fold(String::new(), |mut l, (fg, bg, text, _)| {
-                        l.push_str(&format!(
-                            "{}{}",
-                            termion_color::Fg(fg),
-                            termion_color::Bg(bg)
-                        ));
-                        l.push_str(text);
-                        l
-                    })
-            }).collect();
-        for (i, (new_line, last_line)) in new_frame.iter().zip(self.last_frame.iter()).enumerate() {
-            if new_line != last_line {
-                write!(self.screen, "{}{}", Goto(1, 1 + i as u16), new_line).unwrap();
+        let new_frame = CellGrid::from_frame(&frame, self.size);
+        let mut out = String::new();
+        new_frame.diff(self.last_frame.as_ref(), &mut self.pen, &mut out);
+        write!(self.screen, "{}", out).unwrap();
+        match frame.focus {
+            Some((_, CursorStyle::Hidden)) | None => {
+                write!(self.screen, "{}", Hide).unwrap();
+            }
+            Some((pos, style)) => {
+                if self.cursor_style != style {
+                    write!(self.screen, "\x1b[{} q", cursor_style_code(style)).unwrap();
+                    self.cursor_style = style;
+                }
+                write!(
+                    self.screen,
+                    "{}{}",
+                    Goto(pos.col as u16 + 1, pos.row as u16 + 1),
+                    Show
+                ).unwrap();
             }
-        }
-        if let Some(pos) = frame.focus {
-            write!(
-                self.screen,
-                "{}{}",
-                Goto(pos.col as u16 + 1, pos.row as u16 + 1),
-                Show
-            );
-        } else {
-            write!(self.screen, "{}", Hide);
         }
         self.screen.flush().unwrap();
-        self.last_frame = new_frame;
+        self.last_frame = Some(new_frame);
     }
     fn resize(&mut self, new_size: Size) {
         self.size = new_size;
-        self.last_frame
-            .resize(new_size.rows as usize, "".to_owned());
+        self.last_frame = None;
     }
     fn size(&self) -> Size {
         self.size
     }
 }
 
+impl Drop for TermionBackend {
+    fn drop(&mut self) {
+        // Hand the cursor back the way the user had it
+        if self.cursor_style != CursorStyle::Default {
+            let _ = write!(self.screen, "\x1b[{} q", cursor_style_code(CursorStyle::Default));
+        }
+        let _ = write!(self.screen, "{}", Show);
+        let _ = self.screen.flush();
+    }
+}
+
+// DECSCUSR parameter
+fn cursor_style_code(style: CursorStyle) -> u8 {
+    match style {
+        CursorStyle::Default | CursorStyle::Hidden => 0,
+        CursorStyle::Shaped { shape, blink } => match (shape, blink) {
+            (CursorShape::Block, true) => 1,
+            (CursorShape::Block, false) => 2,
+            (CursorShape::Underline, true) => 3,
+            (CursorShape::Underline, false) => 4,
+            (CursorShape::Bar, true) => 5,
+            (CursorShape::Bar, false) => 6,
+        },
+    }
+}
+
+// OSC 8, with no target closing the current link
+fn push_link(out: &mut String, target: Option<&str>) {
+    out.push_str("\x1b]8;;");
+    out.push_str(target.unwrap_or(""));
+    out.push_str("\x1b\\");
+}
+
+fn push_style_change(out: &mut String, pen: Option<Style>, style: Style) {
+    if pen == Some(style) {
+        return;
+    }
+    let fg = style.fg.unwrap_or(Color::Reset);
+    let bg = style.bg.unwrap_or(Color::Reset);
+    // Attributes can only be switched off reliably with a full reset
+    let (added, old_fg, old_bg) = match pen {
+        Some(pen) if pen.attrs - style.attrs == Attributes::empty() => (
+            style.attrs - pen.attrs,
+            pen.fg.unwrap_or(Color::Reset),
+            pen.bg.unwrap_or(Color::Reset),
+        ),
+        _ => {
+            write!(out, "{}", termion_style::Reset).unwrap();
+            (style.attrs, Color::Reset, Color::Reset)
+        }
+    };
+    if fg != old_fg {
+        write!(out, "{}", termion_color::Fg(fg)).unwrap();
+    }
+    if bg != old_bg {
+        write!(out, "{}", termion_color::Bg(bg)).unwrap();
+    }
+    if added.contains(Attributes::BOLD) {
+        write!(out, "{}", termion_style::Bold).unwrap();
+    }
+    if added.contains(Attributes::DIM) {
+        write!(out, "{}", termion_style::Faint).unwrap();
+    }
+    if added.contains(Attributes::ITALIC) {
+        write!(out, "{}", termion_style::Italic).unwrap();
+    }
+    if added.contains(Attributes::UNDERLINE) {
+        write!(out, "{}", termion_style::Underline).unwrap();
+    }
+    if added.contains(Attributes::REVERSE) {
+        write!(out, "{}", termion_style::Invert).unwrap();
+    }
+}
+
 // This is redundant because termion colors are not sized, and I didn't want to add a box everywhere
 impl TermColor for Color {
     fn write_fg(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -159,3 +366,74 @@ impl TermColor for Color {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::CellGrid;
+    use {Color, Frame, Size, Style};
+
+    fn grid(lines: &[&str], style: Style) -> CellGrid {
+        let size = Size::new(4, lines.len());
+        let image = lines.iter().map(|l| vec![(style, *l, 4, None)]).collect();
+        CellGrid::from_frame(&Frame::new(size, image, None), size)
+    }
+
+    #[test]
+    fn unchanged_frame_is_silent() {
+        let old = grid(&["abcd", "efgh"], Style::new());
+        let new = grid(&["abcd", "efgh"], Style::new());
+        let mut pen = Some(Style::new());
+        let mut out = String::new();
+        new.diff(Some(&old), &mut pen, &mut out);
+        assert_eq!(out, "");
+    }
+
+    #[test]
+    fn only_changed_cells_are_written() {
+        let old = grid(&["abcd", "efgh"], Style::new());
+        let new = grid(&["abcd", "eXYh"], Style::new());
+        let mut pen = Some(Style::new());
+        let mut out = String::new();
+        new.diff(Some(&old), &mut pen, &mut out);
+        assert_eq!(out, "\x1b[2;2HXY");
+    }
+
+    #[test]
+    fn wide_cells_redraw_as_a_unit() {
+        let old = grid(&["a日b"], Style::new());
+        let new = grid(&["ab日"], Style::new());
+        let mut pen = Some(Style::new());
+        let mut out = String::new();
+        new.diff(Some(&old), &mut pen, &mut out);
+        assert_eq!(out, "\x1b[1;2Hb日");
+    }
+
+    #[test]
+    fn style_is_only_emitted_on_change() {
+        let style = Style::new().fg(Color::Red).bold();
+        let new = grid(&["abcd"], style);
+        let mut pen = None;
+        let mut out = String::new();
+        new.diff(None, &mut pen, &mut out);
+        assert_eq!(out, "\x1b[1;1H\x1b[m\x1b[38;5;1m\x1b[1mabcd");
+        assert_eq!(pen, Some(style));
+    }
+
+    #[test]
+    fn links_open_and_close() {
+        let size = Size::new(4, 2);
+        let image = vec![
+            vec![(Style::new(), "a", 1, None), (Style::new(), "bcd", 3, Some("x:/"))],
+            vec![(Style::new(), "efgh", 4, None)],
+        ];
+        let new = CellGrid::from_frame(&Frame::new(size, image, None), size);
+        let old = grid(&["abcd", "wxyz"], Style::new());
+        let mut pen = Some(Style::new());
+        let mut out = String::new();
+        new.diff(Some(&old), &mut pen, &mut out);
+        assert_eq!(
+            out,
+            "\x1b[1;2H\x1b]8;;x:/\x1b\\bcd\x1b]8;;\x1b\\\x1b[2;1Hefgh"
+        );
+    }
+}
@@ -1,10 +1,9 @@
 extern crate text_ui;
-use text_ui::input::Key;
-use text_ui::widget::{List, Log, SimpleInput};
+use text_ui::widget::{Constraint, List, Log, SimpleInput};
 use text_ui::{
     shared, widget::layout::Linear, widget::simple_input::SimpleInputEvent, App, AppEvent, Color,
-    ContentID, EventContext, Executor, InputEvent, Line, Readline, ReadlineEvent, RenderContext,
-    Shared, Size, TermionBackend, TextBlock, Widget,
+    ContentID, EventContext, Executor, InputEvent, KeyCode, KeyEvent, Keymap, Line, Readline,
+    ReadlineEvent, RenderContext, Shared, Size, Style, TermionBackend, TextBlock, Widget,
 };
 
 #[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
@@ -28,8 +27,8 @@ struct DemoApp {
 
 impl DemoApp {
     pub fn new() -> Self {
-        let log1 = shared(Log::new(Some(MyNames::Log1)));
-        let log2 = shared(Log::new(Some(MyNames::Log2)));
+        let log1 = shared(Log::new(Some(MyNames::Log1)).with_min_height(3));
+        let log2 = shared(Log::new(Some(MyNames::Log2)).with_min_height(3));
         let logref1 = log1.clone();
         let logref2 = log2.clone();
         let simpleinput =
@@ -78,7 +77,8 @@ impl Widget<MyNames> for DemoApp {
         let vline = Line::vertical();
         let hline = Line::horizontal();
         let logs: Linear<MyNames> =
-            Linear::hbox(vec![&self.log1, &vline, &self.log2, &vline, &self.nl]);
+            Linear::hbox(vec![&self.log1, &vline, &self.log2, &vline, &self.nl])
+                .constrain(4, Constraint::percent(25));
         let ui = Linear::vbox(vec![
             &logs,
             &hline,
@@ -101,15 +101,15 @@ impl App<MyNames> for DemoApp {
     ) -> text_ui::ShouldPropagate {
         use text_ui::ShouldPropagate::*;
         match event {
-            InputEvent::Key(Key::Esc) => {
+            InputEvent::Key(k) if *k == KeyCode::Esc.into() => {
                 let _ = ctx.send_event(AppEvent::Exit);
                 Stop
             }
-            InputEvent::Key(Key::Ctrl('a')) => {
+            InputEvent::Key(k) if *k == KeyEvent::ctrl('a') => {
                 let _ = ctx.send_event(AppEvent::SetFocus(MyNames::Input1));
                 Stop
             }
-            InputEvent::Key(Key::Ctrl('b')) => {
+            InputEvent::Key(k) if *k == KeyEvent::ctrl('b') => {
                 let _ = ctx.send_event(AppEvent::SetFocus(MyNames::Input2));
                 Stop
             }
@@ -120,23 +120,31 @@ impl App<MyNames> for DemoApp {
             _ => Continue,
         }
     }
+    fn handle_action(&mut self, ctx: &EventContext<MyNames>, action: &'static str) {
+        if action == "quit" {
+            let _ = ctx.send_event(AppEvent::Exit);
+        }
+    }
     fn handle_resize(&mut self, size: Size) {
         self.log2
             .write()
             .unwrap()
             .log_msg(&format!("Resized to: {:?}", size));
     }
-    fn style(&self, cid: ContentID<MyNames>) -> (Option<Color>, Option<Color>) {
+    fn style(&self, cid: ContentID<MyNames>) -> Style {
         match cid.as_tuple() {
-            (Some(MyNames::Log1), ..) => (Some(Color::Red), None),
-            (Some(MyNames::Log2), ..) => (Some(Color::LightGreen), None),
+            (Some(MyNames::Log1), ..) => Style::new().fg(Color::Red),
+            (Some(MyNames::Log2), ..) => Style::new().fg(Color::LightGreen),
             (Some(MyNames::Number(n)), ..) => match n % 3 {
-                0 => (Some(Color::LightBlue), None),
-                1 => (Some(Color::LightRed), None),
-                2 => (Some(Color::LightYellow), None),
+                0 => Style::new().fg(Color::LightBlue),
+                1 => Style::new().fg(Color::LightRed).bold(),
+                2 => Style::new().fg(Color::LightYellow),
                 _ => unreachable!(),
             },
-            (_, _, _) => (None, None),
+            (_, "SimpleInput", _) | (_, "Readline", _) if cid.focused => {
+                Style::new().bg(Color::Blue)
+            }
+            (_, _, _) => Style::new(),
         }
     }
 }
@@ -146,5 +154,11 @@ fn main() {
     app.log1.write().unwrap().log_msg("Ctrl+A here");
     app.log2.write().unwrap().log_msg("Ctrl+B here");
     let mut ex: Executor<MyNames, TermionBackend> = Executor::new();
+    ex.set_keymap(
+        Keymap::new()
+            .with_focus_keys()
+            .bind(None, "C-x C-c", "quit")
+            .unwrap(),
+    );
     ex.run(&mut app, MyNames::Input1);
 }
@@ -1,8 +1,14 @@
+use std::path::PathBuf;
 use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
 
+use keymap::{FOCUS_NEXT, FOCUS_PREV};
+use theme::file_stamp;
 use widget::BackendContext;
 use {
-    App, AppEvent, EventContext, InputEvent, Name, RenderBackend, RenderContext, Size, TextBlock,
+    App, AppEvent, Dispatch, EventContext, FocusEvent, InputEvent, Keymap, Name, RenderBackend,
+    RenderContext, ShouldPropagate, Size, TextBlock,
 };
 
 #[derive(Debug, PartialEq)]
@@ -10,6 +16,8 @@ pub(crate) enum Event<N: Name> {
     Input(InputEvent),
     App(AppEvent<N>), // Ugh I think this needs a better name
     Resize(Size),
+    // Sent by the theme watcher when the file looks different
+    ThemeChanged,
 }
 
 pub struct Executor<N: Name, B: RenderBackend> {
@@ -17,6 +25,7 @@ pub struct Executor<N: Name, B: RenderBackend> {
     receiver: Receiver<Event<N>>,
     sender: Sender<Event<N>>,
     be: B,
+    keymap: Keymap<N>,
 }
 
 impl<N: Name + 'static, B: RenderBackend> Executor<N, B> {
@@ -25,35 +34,88 @@ impl<N: Name + 'static, B: RenderBackend> Executor<N, B> {
         let bc = BackendContext::new(sender.clone());
         let be = B::new(bc);
         let size = be.size();
+        // Focus keys are opt-in, since binding Tab keeps it from widgets like Readline
+        let keymap = Keymap::new();
         Self {
             size,
             sender,
             receiver,
             be: be,
+            keymap,
         }
     }
+    pub fn set_keymap(&mut self, keymap: Keymap<N>) {
+        self.keymap = keymap;
+    }
     pub fn run(&mut self, app: &mut impl App<N>, mut focus: N) {
         let event_ctx = EventContext::new(self.sender.clone());
+        let mut hovered: Vec<N> = vec![];
+        let mut notified_focus = None;
+        let mut last_ui: Option<TextBlock<N>> = None;
+        if let Some(path) = app.theme().and_then(|theme| theme.path()) {
+            watch_theme(path.to_owned(), self.sender.clone());
+        }
         'outer: loop {
             let render_ctx = RenderContext::from_widget(self.size.into(), app);
-            let ui: TextBlock<N> = app.render(render_ctx);
+            let mut ui: TextBlock<N> = app.render(render_ctx).join_lines();
+            focus = settle_focus(&ui, focus);
+            // Focus changes are announced on the block they happened on, below, but the initial
+            // focus or one moved by the render only hears about it here
+            let old_ui = last_ui.as_ref();
+            if notify_focus(&event_ctx, old_ui, &ui, &mut notified_focus, focus) {
+                // The handlers may change how widgets look, so render again after them
+                let render_ctx = RenderContext::from_widget(self.size.into(), app);
+                ui = app.render(render_ctx).join_lines();
+            }
             let frame = ui.render_frame(app, Some(focus));
             self.be.paint_frame(frame);
             {
                 // LOL wait until an event before doing anything this is a dumb hack
-                let event = self.receiver.recv().unwrap();
-                let _ = self.sender.send(event);
+                // A half-typed key sequence only waits until its timeout
+                let event = match self.keymap.deadline() {
+                    Some(deadline) => {
+                        let wait = deadline.saturating_duration_since(Instant::now());
+                        self.receiver.recv_timeout(wait).ok()
+                    }
+                    None => Some(self.receiver.recv().unwrap()),
+                };
+                match event {
+                    Some(event) => {
+                        let _ = self.sender.send(event);
+                    }
+                    None => {
+                        let dispatched = self.keymap.handle_timeout(Instant::now());
+                        app.handle_pending_keys(self.keymap.pending());
+                        dispatch(app, &ui, &event_ctx, &focus, dispatched);
+                    }
+                }
             }
             for event in self.receiver.try_iter() {
                 match event {
                     Event::App(AppEvent::Redraw) => {}
                     Event::App(AppEvent::Exit) => break 'outer,
                     Event::App(AppEvent::SetFocus(f)) => focus = f,
+                    Event::App(AppEvent::FocusNext) => {
+                        focus = ui.next_focus(&focus).unwrap_or(focus);
+                    }
+                    Event::App(AppEvent::FocusPrev) => {
+                        focus = ui.prev_focus(&focus).unwrap_or(focus);
+                    }
+                    Event::ThemeChanged => {
+                        if let Some(Err(err)) = app.theme().map(|theme| theme.reload_if_changed()) {
+                            warn!("Keeping the old theme, reloading failed: {}", err);
+                        }
+                    }
                     Event::Resize(size) => {
                         self.size = size;
                         self.be.resize(size);
                         app.handle_resize(size);
                     }
+                    Event::Input(InputEvent::Key(k)) => {
+                        let dispatched = self.keymap.handle_key(Some(focus), k, Instant::now());
+                        app.handle_pending_keys(self.keymap.pending());
+                        dispatch(app, &ui, &event_ctx, &focus, dispatched);
+                    }
                     Event::Input(event) => {
                         use ShouldPropagate::*;
                         match app.handle_input(&event_ctx, &event) {
@@ -61,13 +123,149 @@ impl<N: Name + 'static, B: RenderBackend> Executor<N, B> {
                             Continue => {}
                         };
                         match event {
-                            InputEvent::Key(k) => ui.handle_key(&event_ctx, &focus, k),
-                            InputEvent::Mouse(m) => ui.handle_mouse(&event_ctx, m),
-                            InputEvent::Unsupported(_) => {}
+                            InputEvent::Mouse(m) => {
+                                hovered = ui.handle_hover(&event_ctx, &hovered, m.pos);
+                                ui.handle_mouse(&event_ctx, m)
+                            }
+                            InputEvent::Key(_) | InputEvent::Unsupported(_) => {}
                         }
                     }
                 }
             }
+            // The old name gets told even if it's gone from the next render
+            notify_focus(&event_ctx, Some(&ui), &ui, &mut notified_focus, focus);
+            last_ui = Some(ui);
+        }
+    }
+}
+
+// Focus left on a name a modal covered moves to the first name that can take it. Any other name
+// keeps it, even one that never takes focus itself, since an app can focus what it likes.
+fn settle_focus<N: Name>(ui: &TextBlock<N>, focus: N) -> N {
+    if ui.is_covered(&focus) {
+        ui.next_focus(&focus).unwrap_or(focus)
+    } else {
+        focus
+    }
+}
+
+// Tells the name last told it had focus that it lost it, on the block it was rendered on, and then
+// the focused name that it gained it. Names without focus handlers count as told too, so nothing
+// is sent twice. Returns whether any handler heard about the new focus.
+fn notify_focus<N: Name>(
+    event_ctx: &EventContext<N>,
+    old_ui: Option<&TextBlock<N>>,
+    ui: &TextBlock<N>,
+    notified: &mut Option<N>,
+    focus: N,
+) -> bool {
+    if *notified == Some(focus) {
+        return false;
+    }
+    if let (Some(old), Some(old_ui)) = (notified.take(), old_ui) {
+        old_ui.handle_focus(event_ctx, old, FocusEvent::Lost);
+    }
+    *notified = Some(focus);
+    ui.handle_focus(event_ctx, focus, FocusEvent::Gained)
+}
+
+// Tells the executor to reload the theme when its file changes, until the executor is gone
+fn watch_theme<N: Name + 'static>(path: PathBuf, sender: Sender<Event<N>>) {
+    thread::spawn(move || {
+        let mut stamp = file_stamp(&path).ok();
+        loop {
+            thread::sleep(Duration::from_millis(250));
+            let new_stamp = file_stamp(&path).ok();
+            if new_stamp != stamp {
+                stamp = new_stamp;
+                if sender.send(Event::ThemeChanged).is_err() {
+                    break;
+                }
+            }
         }
+    });
+}
+
+// Keys the keymap let through go to the app and then the focused widget, as any other input
+fn dispatch<N: Name>(
+    app: &mut impl App<N>,
+    ui: &TextBlock<N>,
+    event_ctx: &EventContext<N>,
+    focus: &N,
+    dispatched: Vec<Dispatch>,
+) {
+    for d in dispatched {
+        match d {
+            Dispatch::Action(FOCUS_NEXT) => {
+                let _ = event_ctx.send_event(AppEvent::FocusNext);
+            }
+            Dispatch::Action(FOCUS_PREV) => {
+                let _ = event_ctx.send_event(AppEvent::FocusPrev);
+            }
+            Dispatch::Action(action) => app.handle_action(event_ctx, action),
+            Dispatch::Key(k) => match app.handle_input(event_ctx, &InputEvent::Key(k)) {
+                ShouldPropagate::Stop => {}
+                ShouldPropagate::Continue => ui.handle_key(event_ctx, focus, k),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{notify_focus, settle_focus};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use test_util::event_context;
+    use {ContentID, FocusCallback, FocusEvent, Pos, RenderBound, TextBlock};
+
+    #[test]
+    fn focus_notifications() {
+        let seen = Rc::new(RefCell::new(vec![]));
+        let log = |name: u8| -> FocusCallback<u8> {
+            let seen = seen.clone();
+            Box::new(move |_, ev| seen.borrow_mut().push((name, ev)))
+        };
+        let id = ContentID::new(None, "Test", "Test");
+        let bound = RenderBound::new(None, None);
+        let ui = TextBlock::clip_lines(id, vec!["ab".to_owned()], bound)
+            .add_focus_handler(1, log(1))
+            .add_focus_handler(2, log(2));
+        let (ctx, _receiver) = event_context();
+        let mut notified = None;
+        // Name 3 has no handler, but still counts as told, so it isn't sent again
+        assert!(!notify_focus(&ctx, None, &ui, &mut notified, 3));
+        assert_eq!(notified, Some(3));
+        assert!(!notify_focus(&ctx, Some(&ui), &ui, &mut notified, 3));
+        assert!(notify_focus(&ctx, Some(&ui), &ui, &mut notified, 1));
+        assert!(notify_focus(&ctx, Some(&ui), &ui, &mut notified, 2));
+        assert!(!notify_focus(&ctx, Some(&ui), &ui, &mut notified, 3));
+        assert_eq!(
+            *seen.borrow(),
+            vec![
+                (1, FocusEvent::Gained),
+                (1, FocusEvent::Lost),
+                (2, FocusEvent::Gained),
+                (2, FocusEvent::Lost),
+            ]
+        );
+    }
+
+    #[test]
+    fn focus_moves_only_from_under_a_modal() {
+        let id = ContentID::new(None, "Test", "Test");
+        let bound = RenderBound::new(None, None);
+        let lines = |n| vec!["ab".to_owned(); n];
+        let ui = TextBlock::clip_lines(id, lines(2), bound)
+            .add_cursor(1, Pos::new(0, 0))
+            .add_cursor(2, Pos::new(0, 1));
+        // Like the target of a SetFocus that never renders a cursor
+        assert_eq!(settle_focus(&ui, 7), 7);
+        assert_eq!(settle_focus(&ui, 1), 1);
+
+        let modal = TextBlock::clip_lines(id, lines(1), bound).add_cursor(3, Pos::new(0, 0));
+        let ui = ui.overlay(modal, Pos::new(0, 0));
+        assert_eq!(settle_focus(&ui, 1), 2);
+        assert_eq!(settle_focus(&ui, 7), 7);
     }
 }
@@ -101,6 +101,10 @@ where
         }
     }
 
+    pub fn iter_all(&self) -> slice::Iter<I> {
+        self.items.iter()
+    }
+
     pub fn iter_all_mut(&mut self) -> slice::IterMut<I> {
         self.items.iter_mut()
     }
@@ -1,3 +1,585 @@
-pub use termion::event::Event as InputEvent;
-pub use termion::event::{Key, MouseButton, MouseEvent};
-// XXX TODO declare new mouse type instead of re-exporting termion's
+use std::fmt;
+use std::mem;
+use std::str;
+
+use termion::event::{Event as TermionEvent, Key as TermionKey};
+
+use Pos;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputEvent {
+    Key(KeyEvent),
+    Mouse(MouseEvent),
+    Unsupported(Vec<u8>),
+}
+
+bitflags! {
+    #[derive(Default)]
+    pub struct Modifiers: u8 {
+        const SHIFT = 0b001;
+        const ALT = 0b010;
+        const CTRL = 0b100;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyCode {
+    Char(char),
+    Enter,
+    Tab,
+    Backspace,
+    Esc,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Delete,
+    Insert,
+    F(u8),
+    Null,
+}
+
+// Control characters arrive as the letter they were typed with, so Ctrl-A is Char('a') with CTRL
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyEvent {
+    pub code: KeyCode,
+    pub modifiers: Modifiers,
+}
+
+impl KeyEvent {
+    pub fn new(code: KeyCode, modifiers: Modifiers) -> Self {
+        Self { code, modifiers }
+    }
+    pub fn ctrl(ch: char) -> Self {
+        Self::new(KeyCode::Char(ch), Modifiers::CTRL)
+    }
+    pub fn alt(ch: char) -> Self {
+        Self::new(KeyCode::Char(ch), Modifiers::ALT)
+    }
+}
+
+impl From<KeyCode> for KeyEvent {
+    fn from(code: KeyCode) -> Self {
+        Self::new(code, Modifiers::empty())
+    }
+}
+
+const KEY_NAMES: &[(KeyCode, &str)] = &[
+    (KeyCode::Char(' '), "Space"),
+    (KeyCode::Enter, "Enter"),
+    (KeyCode::Tab, "Tab"),
+    (KeyCode::Backspace, "Backspace"),
+    (KeyCode::Esc, "Esc"),
+    (KeyCode::Left, "Left"),
+    (KeyCode::Right, "Right"),
+    (KeyCode::Up, "Up"),
+    (KeyCode::Down, "Down"),
+    (KeyCode::Home, "Home"),
+    (KeyCode::End, "End"),
+    (KeyCode::PageUp, "PageUp"),
+    (KeyCode::PageDown, "PageDown"),
+    (KeyCode::Delete, "Delete"),
+    (KeyCode::Insert, "Insert"),
+    (KeyCode::Null, "Null"),
+];
+
+// Emacs-style notation, e.g. "C-x", "M-S-Left", "F5" or "Space"
+impl fmt::Display for KeyEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.contains(Modifiers::CTRL) {
+            write!(f, "C-")?;
+        }
+        if self.modifiers.contains(Modifiers::ALT) {
+            write!(f, "M-")?;
+        }
+        if self.modifiers.contains(Modifiers::SHIFT) {
+            write!(f, "S-")?;
+        }
+        match (
+            self.code,
+            KEY_NAMES.iter().find(|(code, _)| *code == self.code),
+        ) {
+            (_, Some((_, name))) => write!(f, "{}", name),
+            (KeyCode::Char(ch), None) => write!(f, "{}", ch),
+            (KeyCode::F(n), None) => write!(f, "F{}", n),
+            (code, None) => write!(f, "{:?}", code),
+        }
+    }
+}
+
+// A key that isn't in KeyEvent's notation
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyParseError {
+    pub token: String,
+}
+
+impl fmt::Display for KeyParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown key {:?}", self.token)
+    }
+}
+
+impl str::FromStr for KeyEvent {
+    type Err = KeyParseError;
+    fn from_str(s: &str) -> Result<Self, KeyParseError> {
+        let error = || KeyParseError {
+            token: s.to_owned(),
+        };
+        let mut modifiers = Modifiers::empty();
+        let mut rest = s;
+        while rest.len() > 2 && rest.as_bytes()[1] == b'-' {
+            match rest.as_bytes()[0] {
+                b'C' => modifiers |= Modifiers::CTRL,
+                b'M' | b'A' => modifiers |= Modifiers::ALT,
+                b'S' => modifiers |= Modifiers::SHIFT,
+                _ => break,
+            }
+            rest = &rest[2..];
+        }
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(ch), None) => KeyCode::Char(ch),
+            _ => match KEY_NAMES.iter().find(|(_, name)| *name == rest) {
+                Some((code, _)) => *code,
+                None if rest.starts_with('F') => {
+                    KeyCode::F(rest[1..].parse().map_err(|_| error())?)
+                }
+                None => return Err(error()),
+            },
+        };
+        // Terminals send a shifted letter as the capital, so "S-a" has to mean 'A' to match
+        let code = match code {
+            KeyCode::Char(ch) if modifiers.contains(Modifiers::SHIFT) => {
+                let mut upper = ch.to_uppercase();
+                match (upper.next(), upper.next()) {
+                    (Some(up), None) if up != ch => {
+                        modifiers.remove(Modifiers::SHIFT);
+                        KeyCode::Char(up)
+                    }
+                    _ => code,
+                }
+            }
+            _ => code,
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+// Parses whitespace-separated keys, like "C-x C-s" or "g g"
+pub fn parse_keys(spec: &str) -> Result<Vec<KeyEvent>, KeyParseError> {
+    spec.split_whitespace().map(str::parse).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollDirection {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseAction {
+    Press(MouseButton),
+    Release(MouseButton),
+    // The first motion with a button held starts a drag, and releasing the button ends it
+    DragStart(MouseButton),
+    Drag(MouseButton),
+    DragEnd(MouseButton),
+    Scroll(ScrollDirection),
+    // Motion with no button held, only reported by terminals tracking all motion
+    Move,
+}
+
+// Positions start at (0, 0), and are relative to the widget once they reach a MouseCallback
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseEvent {
+    pub action: MouseAction,
+    pub pos: Pos,
+    pub modifiers: Modifiers,
+}
+
+impl MouseEvent {
+    pub fn new(action: MouseAction, pos: Pos, modifiers: Modifiers) -> Self {
+        Self {
+            action,
+            pos,
+            modifiers,
+        }
+    }
+    pub fn relative_to(self, origin: Pos) -> Self {
+        Self {
+            pos: self.pos - origin,
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusEvent {
+    Gained,
+    Lost,
+}
+
+// Pointer tracking for a widget, with positions relative to the widget
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HoverEvent {
+    Enter(Pos),
+    Move(Pos),
+    Leave,
+}
+
+// Turns the backend's input into our events. Mouse reports and modified keys are parsed from the
+// raw bytes, since termion drops the modifier bits, and the held button is remembered to report
+// drags.
+#[derive(Debug, Default)]
+pub(crate) struct InputDecoder {
+    held: Option<MouseButton>,
+    dragging: bool,
+}
+
+struct MouseReport {
+    code: u16,
+    pos: Pos,
+    release: bool,
+}
+
+impl InputDecoder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    pub fn decode(&mut self, event: TermionEvent, raw: Vec<u8>) -> InputEvent {
+        if let Some(report) = parse_mouse(&raw) {
+            return InputEvent::Mouse(self.mouse_event(report));
+        }
+        let key = parse_key(&raw).or_else(|| match event {
+            TermionEvent::Key(k) => key_from_termion(k),
+            _ => None,
+        });
+        match key {
+            Some(key) => InputEvent::Key(key),
+            None => InputEvent::Unsupported(raw),
+        }
+    }
+    fn mouse_event(&mut self, report: MouseReport) -> MouseEvent {
+        let code = report.code;
+        let mut modifiers = Modifiers::empty();
+        modifiers.set(Modifiers::SHIFT, code & 4 != 0);
+        modifiers.set(Modifiers::ALT, code & 8 != 0);
+        modifiers.set(Modifiers::CTRL, code & 16 != 0);
+        let button = match code & 0b11 {
+            0 => Some(MouseButton::Left),
+            1 => Some(MouseButton::Middle),
+            2 => Some(MouseButton::Right),
+            _ => None,
+        };
+        let motion = code & 32 != 0;
+        let action = if code & 64 != 0 {
+            match code & 1 {
+                0 => MouseAction::Scroll(ScrollDirection::Up),
+                _ => MouseAction::Scroll(ScrollDirection::Down),
+            }
+        } else if report.release || (button.is_none() && !motion) {
+            // Only SGR releases say which button went up
+            let held = self.held.take();
+            let button = button.or(held).unwrap_or(MouseButton::Left);
+            if mem::replace(&mut self.dragging, false) {
+                MouseAction::DragEnd(button)
+            } else {
+                MouseAction::Release(button)
+            }
+        } else if motion {
+            match button.or(self.held) {
+                Some(button) => {
+                    if mem::replace(&mut self.dragging, true) {
+                        MouseAction::Drag(button)
+                    } else {
+                        MouseAction::DragStart(button)
+                    }
+                }
+                None => MouseAction::Move,
+            }
+        } else {
+            let button = button.unwrap_or(MouseButton::Left);
+            self.held = Some(button);
+            self.dragging = false;
+            MouseAction::Press(button)
+        };
+        MouseEvent::new(action, report.pos, modifiers)
+    }
+}
+
+fn parse_mouse(raw: &[u8]) -> Option<MouseReport> {
+    // Coordinates are 1-based in every encoding
+    let report = |code, x: u16, y: u16, release| MouseReport {
+        code,
+        pos: Pos::new(x.saturating_sub(1) as usize, y.saturating_sub(1) as usize),
+        release,
+    };
+    if raw.starts_with(b"\x1b[M") {
+        // X10: ESC [ M Cb Cx Cy, each byte offset by 32
+        if raw.len() != 6 {
+            return None;
+        }
+        let code = raw[3].checked_sub(32)? as u16;
+        let x = raw[4].saturating_sub(32) as u16;
+        let y = raw[5].saturating_sub(32) as u16;
+        Some(report(code, x, y, false))
+    } else if raw.starts_with(b"\x1b[<") {
+        // SGR: ESC [ < Cb ; Cx ; Cy, then M for press or m for release
+        let (last, body) = raw[3..].split_last()?;
+        let (code, x, y) = parse_params(body)?;
+        match last {
+            b'M' => Some(report(code, x, y, false)),
+            b'm' => Some(report(code, x, y, true)),
+            _ => None,
+        }
+    } else if raw.starts_with(b"\x1b[") && raw.ends_with(b"M") {
+        // rxvt: ESC [ Cb ; Cx ; Cy M, with Cb offset by 32
+        let (code, x, y) = parse_params(&raw[2..raw.len() - 1])?;
+        Some(report(code.checked_sub(32)?, x, y, false))
+    } else {
+        None
+    }
+}
+
+fn key_from_termion(key: TermionKey) -> Option<KeyEvent> {
+    let key = match key {
+        TermionKey::Char('\n') => KeyCode::Enter.into(),
+        TermionKey::Char('\t') => KeyCode::Tab.into(),
+        TermionKey::Char(ch) => KeyCode::Char(ch).into(),
+        TermionKey::Alt(ch) => KeyEvent::alt(ch),
+        TermionKey::Ctrl(ch) => KeyEvent::ctrl(ch),
+        TermionKey::BackTab => KeyEvent::new(KeyCode::Tab, Modifiers::SHIFT),
+        TermionKey::Backspace => KeyCode::Backspace.into(),
+        TermionKey::Esc => KeyCode::Esc.into(),
+        TermionKey::Left => KeyCode::Left.into(),
+        TermionKey::Right => KeyCode::Right.into(),
+        TermionKey::Up => KeyCode::Up.into(),
+        TermionKey::Down => KeyCode::Down.into(),
+        TermionKey::Home => KeyCode::Home.into(),
+        TermionKey::End => KeyCode::End.into(),
+        TermionKey::PageUp => KeyCode::PageUp.into(),
+        TermionKey::PageDown => KeyCode::PageDown.into(),
+        TermionKey::Delete => KeyCode::Delete.into(),
+        TermionKey::Insert => KeyCode::Insert.into(),
+        TermionKey::F(n) => KeyCode::F(n).into(),
+        TermionKey::Null => KeyCode::Null.into(),
+        _ => return None,
+    };
+    Some(key)
+}
+
+// The modified CSI forms termion rejects:
+//   ESC [ 1 ; m A        arrows, Home, End and F1-F4
+//   ESC [ n ; m ~        editing keys and F5-F12
+//   ESC [ 27 ; m ; c ~   xterm's modifyOtherKeys
+//   ESC [ c ; m u        fixterms / CSI u
+fn parse_key(raw: &[u8]) -> Option<KeyEvent> {
+    if !raw.starts_with(b"\x1b[") {
+        return None;
+    }
+    let (last, body) = raw[2..].split_last()?;
+    let params = str::from_utf8(body)
+        .ok()?
+        .split(';')
+        .map(|n| n.parse::<u32>().ok())
+        .collect::<Option<Vec<u32>>>()?;
+    let (code, modifier_param) = match (*last, &params[..]) {
+        (b'u', &[c]) => (code_point_key(c)?, 1),
+        (b'u', &[c, m]) => (code_point_key(c)?, m),
+        (b'~', &[27, m, c]) => (code_point_key(c)?, m),
+        (b'~', &[n, m]) => (tilde_key(n)?, m),
+        (b'A'..=b'Z', &[1, m]) => (letter_key(*last)?, m),
+        _ => return None,
+    };
+    // The parameter is one more than a bitmask of Shift, Alt, Ctrl and Meta
+    let bits = modifier_param.saturating_sub(1);
+    let mut modifiers = Modifiers::empty();
+    modifiers.set(Modifiers::SHIFT, bits & 1 != 0);
+    modifiers.set(Modifiers::ALT, bits & 0b1010 != 0);
+    modifiers.set(Modifiers::CTRL, bits & 4 != 0);
+    Some(KeyEvent::new(code, modifiers))
+}
+
+fn code_point_key(c: u32) -> Option<KeyCode> {
+    let code = match c {
+        9 => KeyCode::Tab,
+        13 => KeyCode::Enter,
+        27 => KeyCode::Esc,
+        127 => KeyCode::Backspace,
+        c => KeyCode::Char(::std::char::from_u32(c)?),
+    };
+    Some(code)
+}
+
+fn tilde_key(n: u32) -> Option<KeyCode> {
+    let code = match n {
+        1 | 7 => KeyCode::Home,
+        2 => KeyCode::Insert,
+        3 => KeyCode::Delete,
+        4 | 8 => KeyCode::End,
+        5 => KeyCode::PageUp,
+        6 => KeyCode::PageDown,
+        11..=15 => KeyCode::F(n as u8 - 10),
+        17..=21 => KeyCode::F(n as u8 - 11),
+        23..=24 => KeyCode::F(n as u8 - 12),
+        _ => return None,
+    };
+    Some(code)
+}
+
+fn letter_key(letter: u8) -> Option<KeyCode> {
+    let code = match letter {
+        b'A' => KeyCode::Up,
+        b'B' => KeyCode::Down,
+        b'C' => KeyCode::Right,
+        b'D' => KeyCode::Left,
+        b'H' => KeyCode::Home,
+        b'F' => KeyCode::End,
+        b'P'..=b'S' => KeyCode::F(letter - b'P' + 1),
+        _ => return None,
+    };
+    Some(code)
+}
+
+fn parse_params(body: &[u8]) -> Option<(u16, u16, u16)> {
+    let text = str::from_utf8(body).ok()?;
+    let mut nums = text.split(';').map(|n| n.parse::<u16>().ok());
+    match (nums.next(), nums.next(), nums.next(), nums.next()) {
+        (Some(Some(code)), Some(Some(x)), Some(Some(y)), None) => Some((code, x, y)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_keys, InputDecoder, InputEvent, KeyCode, KeyEvent, Modifiers, MouseAction,
+        MouseButton, ScrollDirection,
+    };
+    use termion::event::{Event, Key};
+    use Pos;
+
+    fn decode(decoder: &mut InputDecoder, raw: &[u8]) -> (MouseAction, Pos, Modifiers) {
+        match decoder.decode(Event::Unsupported(raw.to_vec()), raw.to_vec()) {
+            InputEvent::Mouse(m) => (m.action, m.pos, m.modifiers),
+            other => panic!("not a mouse event: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn keys_pass_through() {
+        let mut decoder = InputDecoder::new();
+        let mut key = |key, raw: &[u8]| decoder.decode(Event::Key(key), raw.to_vec());
+        assert_eq!(
+            key(Key::Char('q'), b"q"),
+            InputEvent::Key(KeyCode::Char('q').into())
+        );
+        assert_eq!(
+            key(Key::Char('\n'), b"\r"),
+            InputEvent::Key(KeyCode::Enter.into())
+        );
+        assert_eq!(
+            key(Key::Ctrl('a'), b"\x01"),
+            InputEvent::Key(KeyEvent::ctrl('a'))
+        );
+        assert_eq!(
+            key(Key::Alt('x'), b"\x1bx"),
+            InputEvent::Key(KeyEvent::alt('x'))
+        );
+    }
+
+    #[test]
+    fn key_notation() {
+        let keys = parse_keys("C-x M-S-Left Space F5 C--").unwrap();
+        assert_eq!(keys[0], KeyEvent::ctrl('x'));
+        assert_eq!(
+            keys[1],
+            KeyEvent::new(KeyCode::Left, Modifiers::ALT | Modifiers::SHIFT)
+        );
+        assert_eq!(keys[2], KeyCode::Char(' ').into());
+        assert_eq!(keys[3], KeyCode::F(5).into());
+        assert_eq!(keys[4], KeyEvent::ctrl('-'));
+        let shown: Vec<String> = keys.iter().map(|k| k.to_string()).collect();
+        assert_eq!(shown, vec!["C-x", "M-S-Left", "Space", "F5", "C--"]);
+        let bogus = parse_keys("C-x Bogus").unwrap_err();
+        assert_eq!(bogus.token, "Bogus");
+        assert_eq!(parse_keys("F9x").unwrap_err().token, "F9x");
+        let shifted = parse_keys("S-a M-S-b S-1").unwrap();
+        assert_eq!(shifted[0], KeyCode::Char('A').into());
+        assert_eq!(shifted[1], KeyEvent::alt('B'));
+        assert_eq!(shifted[2], KeyEvent::new(KeyCode::Char('1'), Modifiers::SHIFT));
+    }
+
+    #[test]
+    fn modified_keys() {
+        let mut decoder = InputDecoder::new();
+        let mut key = |raw: &[u8]| match decoder.decode(Event::Unsupported(vec![]), raw.to_vec()) {
+            InputEvent::Key(k) => (k.code, k.modifiers),
+            other => panic!("not a key event: {:?}", other),
+        };
+        let ctrl_shift = Modifiers::CTRL | Modifiers::SHIFT;
+        assert_eq!(key(b"\x1b[1;6C"), (KeyCode::Right, ctrl_shift));
+        assert_eq!(key(b"\x1b[1;3D"), (KeyCode::Left, Modifiers::ALT));
+        assert_eq!(key(b"\x1b[3;5~"), (KeyCode::Delete, Modifiers::CTRL));
+        assert_eq!(key(b"\x1b[1;2P"), (KeyCode::F(1), Modifiers::SHIFT));
+        assert_eq!(key(b"\x1b[27;5;13~"), (KeyCode::Enter, Modifiers::CTRL));
+        assert_eq!(key(b"\x1b[13;5u"), (KeyCode::Enter, Modifiers::CTRL));
+    }
+
+    #[test]
+    fn sgr_drag() {
+        use self::MouseAction::*;
+        let mut decoder = InputDecoder::new();
+        let left = MouseButton::Left;
+        let none = Modifiers::empty();
+        assert_eq!(
+            decode(&mut decoder, b"\x1b[<0;3;2M"),
+            (Press(left), Pos::new(2, 1), none)
+        );
+        assert_eq!(decode(&mut decoder, b"\x1b[<32;4;2M").0, DragStart(left));
+        assert_eq!(decode(&mut decoder, b"\x1b[<32;5;2M").0, Drag(left));
+        assert_eq!(decode(&mut decoder, b"\x1b[<0;5;2m").0, DragEnd(left));
+        assert_eq!(
+            decode(&mut decoder, b"\x1b[<2;1;1M").0,
+            Press(MouseButton::Right)
+        );
+        assert_eq!(
+            decode(&mut decoder, b"\x1b[<2;1;1m").0,
+            Release(MouseButton::Right)
+        );
+    }
+
+    #[test]
+    fn modifiers_and_scroll() {
+        let mut decoder = InputDecoder::new();
+        let (action, _, mods) = decode(&mut decoder, b"\x1b[<20;1;1M");
+        assert_eq!(action, MouseAction::Press(MouseButton::Left));
+        assert_eq!(mods, Modifiers::SHIFT | Modifiers::CTRL);
+        let (action, _, mods) = decode(&mut decoder, b"\x1b[<73;1;1M");
+        assert_eq!(action, MouseAction::Scroll(ScrollDirection::Down));
+        assert_eq!(mods, Modifiers::ALT);
+    }
+
+    #[test]
+    fn x10_release_uses_held_button() {
+        let mut decoder = InputDecoder::new();
+        assert_eq!(
+            decode(&mut decoder, b"\x1b[M!+%").0,
+            MouseAction::Press(MouseButton::Middle)
+        );
+        let (action, pos, _) = decode(&mut decoder, b"\x1b[M#+%");
+        assert_eq!(action, MouseAction::Release(MouseButton::Middle));
+        assert_eq!(pos, Pos::new(10, 4));
+    }
+}
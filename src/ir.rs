@@ -1,27 +1,108 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::iter::repeat;
+use std::mem;
 
+use itertools::Itertools;
+use unicode_linebreak::{linebreaks, BreakOpportunity};
 use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 use indextree::IndexTree;
+use junctions::join_lines;
 use {
-    App, Color, Direction, EventContext, Key, KeyCallback, MouseCallback, MouseEvent, Name, Pos,
-    RenderBound, Size,
+    App, CursorStyle, Direction, EventContext, FocusCallback, FocusEvent, HoverCallback, HoverEvent,
+    KeyCallback, KeyEvent, MouseCallback, MouseEvent, Name, Pos, Rect, RenderBound,
+    ShouldPropagate, Size, Style,
 };
 
+// Grapheme clusters never take more than two cells, even when built from several wide chars
+pub(crate) fn grapheme_width(grapheme: &str) -> usize {
+    std::cmp::min(UnicodeWidthStr::width(grapheme), 2)
+}
+
+pub fn text_width(text: &str) -> usize {
+    UnicodeSegmentation::graphemes(text, true)
+        .map(grapheme_width)
+        .sum()
+}
+
+pub const DEFAULT_TAB_WIDTH: usize = 8;
+
+// Expands tabs and replaces control characters with caret or code point notation, so that
+// arbitrary text can't move the cursor or smuggle escape sequences to the terminal.
+// `col` is where the text starts on its line, so that tabs land on the right stops.
+pub(crate) fn sanitize(text: &str, col: usize, tab_width: usize) -> String {
+    if !text.chars().any(char::is_control) {
+        return text.to_owned();
+    }
+    let mut out = String::with_capacity(text.len());
+    // The column reached so far, kept up as we go rather than measured again at each tab
+    let mut at = col;
+    for grapheme in UnicodeSegmentation::graphemes(text, true) {
+        if !grapheme.chars().any(char::is_control) {
+            out.push_str(grapheme);
+            at += grapheme_width(grapheme);
+            continue;
+        }
+        for c in grapheme.chars() {
+            let start = out.len();
+            match c {
+                '\t' => {
+                    let spaces = if tab_width == 0 {
+                        1
+                    } else {
+                        tab_width - at % tab_width
+                    };
+                    pad_to_width(&mut out, spaces);
+                }
+                '\x00'..='\x1f' => {
+                    out.push('^');
+                    out.push((c as u8 ^ 0x40) as char);
+                }
+                '\x7f' => out.push_str("^?"),
+                c if c.is_control() => out.push_str(&format!("<U+{:04X}>", c as u32)),
+                c => out.push(c),
+            }
+            at += text_width(&out[start..]);
+        }
+    }
+    out
+}
+
+fn pad_to_width(text: &mut String, width: usize) {
+    text.extend(repeat(' ').take(width));
+}
+
 // XXX TODO I think this could return impl Iterator<Item=String> instead, but lifetime trouble
-fn split_line_graphemes<'a>(line: &'a str, width: usize) -> Vec<String> {
-    let mut letters: Vec<&'a str> = UnicodeSegmentation::graphemes(line, true).collect();
-    let len = letters.len();
-    match len % width {
-        0 => {}
-        n => letters.resize(len + (width - n), " "),
-    };
-    letters
-        .chunks(width)
-        .map(|ls: &[&'a str]| ls.concat())
-        .collect()
+pub(crate) fn split_line_graphemes(line: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    if width == 0 {
+        return lines;
+    }
+    // Stands in for graphemes too wide for any line, so their text doesn't just vanish
+    let placeholder = " ".repeat(width);
+    let mut current = String::new();
+    let mut current_width = 0;
+    for grapheme in UnicodeSegmentation::graphemes(line, true) {
+        let (grapheme, w) = match grapheme_width(grapheme) {
+            w if w > width => (placeholder.as_str(), width),
+            w => (grapheme, w),
+        };
+        if current_width + w > width {
+            pad_to_width(&mut current, width - current_width);
+            lines.push(current);
+            current = String::new();
+            current_width = 0;
+        }
+        current.push_str(grapheme);
+        current_width += w;
+    }
+    if !current.is_empty() {
+        pad_to_width(&mut current, width - current_width);
+        lines.push(current);
+    }
+    lines
 }
 
 // XXX TODO Better name??
@@ -30,6 +111,8 @@ pub struct ContentID<N: Name> {
     pub name: Option<N>,
     pub widget_type: &'static str,
     pub class: &'static str,
+    // Set while drawing a frame, for content belonging to the focused name
+    pub focused: bool,
 }
 
 impl<N: Name> ContentID<N> {
@@ -37,10 +120,12 @@ impl<N: Name> ContentID<N> {
         (self.name, self.widget_type, self.class)
     }
     pub fn new(name: Option<N>, widget_type: &'static str, class: &'static str) -> Self {
+        let focused = false;
         Self {
             name,
             widget_type,
             class,
+            focused,
         }
     }
 }
@@ -50,6 +135,8 @@ pub struct Segment<N: Name> {
     pub id: ContentID<N>,
     pub text: String,
     pub len: usize,
+    // Hyperlink target, for terminals that support them
+    pub link: Option<String>,
 }
 
 impl<N: Name> Segment<N> {
@@ -58,12 +145,73 @@ impl<N: Name> Segment<N> {
         Self::new_id(id, text)
     }
     pub fn new_id(id: ContentID<N>, text: String) -> Self {
-        let len = UnicodeSegmentation::graphemes(text.as_str(), true).count();
-        Self { id, text, len }
+        Self::new_at(id, text, 0, DEFAULT_TAB_WIDTH)
+    }
+    // Builds a segment that will start at column `col` of its line
+    pub fn new_at(id: ContentID<N>, text: String, col: usize, tab_width: usize) -> Self {
+        let text = sanitize(&text, col, tab_width);
+        let len = text_width(&text);
+        let link = None;
+        Self {
+            id,
+            text,
+            len,
+            link,
+        }
     }
     pub fn blank_sized(id: ContentID<N>, len: usize) -> Self {
         let text: String = repeat(' ').take(len).collect();
-        Self { id, text, len }
+        let link = None;
+        Self {
+            id,
+            text,
+            len,
+            link,
+        }
+    }
+    pub fn with_link<S: Into<String>>(mut self, target: S) -> Self {
+        // The target ends up inside an escape sequence, so it must not be able to end it early
+        let target: String = target.into().chars().filter(|c| !c.is_control()).collect();
+        self.link = Some(target);
+        self
+    }
+    pub fn split_at(self, col: usize) -> (Self, Self) {
+        let mut left = String::new();
+        let mut width = 0;
+        let mut right = String::new();
+        for (idx, grapheme) in UnicodeSegmentation::grapheme_indices(self.text.as_str(), true) {
+            let w = grapheme_width(grapheme);
+            if width >= col {
+                right.push_str(&self.text[idx..]);
+                break;
+            } else if width + w > col {
+                // A wide cell straddling the edge is replaced with blanks, never split
+                pad_to_width(&mut left, col - width);
+                pad_to_width(&mut right, width + w - col);
+                right.push_str(&self.text[idx + grapheme.len()..]);
+                width = col;
+                break;
+            }
+            left.push_str(grapheme);
+            width += w;
+        }
+        let id = self.id;
+        let left_len = width;
+        let right_len = self.len - left_len;
+        (
+            Self {
+                id,
+                text: left,
+                len: left_len,
+                link: self.link.clone(),
+            },
+            Self {
+                id,
+                text: right,
+                len: right_len,
+                link: self.link,
+            },
+        )
     }
 }
 
@@ -82,29 +230,160 @@ impl<N: Name> TextLine<N> {
                 .id;
             let filler = Segment::blank_sized(last_id, len - self.len);
             self.push(filler);
+            self
         } else if self.len > len {
-            let mut accum: usize = 0;
-            self.segments = self
-                .segments
-                .into_iter()
-                .filter_map(move |mut s| {
-                    if accum >= len {
-                        None
-                    } else if accum + s.len <= len {
-                        accum += s.len;
-                        Some(s)
-                    } else {
-                        s.text = UnicodeSegmentation::graphemes(s.text.as_str(), true)
-                            .take(len - accum)
-                            .collect();
-                        s.len = len - accum;
-                        accum = len;
-                        Some(s)
+            self.split_at(len).0
+        } else {
+            self
+        }
+    }
+    // Cuts the line down to `len` columns, marking the cut as `overflow` asks
+    pub fn truncate(self, len: usize, overflow: Overflow) -> Self {
+        if self.len <= len {
+            return self;
+        }
+        if len == 0 {
+            return Self::default();
+        }
+        let total = self.len;
+        match overflow {
+            Overflow::Cut => self.split_at(len).0,
+            Overflow::MiddleEllipsis if len > 1 => {
+                let head = (len - 1) / 2;
+                let tail = len - 1 - head;
+                let (mut left, rest) = self.split_at(head);
+                let (_, right) = rest.split_at(total - head - tail);
+                let id = left.segments.last().or_else(|| right.segments.first()).map(|s| s.id);
+                // With no segment to take an id from there's nothing to mark, so it's just cut
+                if let Some(id) = id {
+                    left.push(Segment::new_id(id, ELLIPSIS.to_owned()));
+                }
+                left.hconcat(right);
+                left
+            }
+            // A single column only has room for the ellipsis, which then goes at the end
+            Overflow::Ellipsis | Overflow::MiddleEllipsis => {
+                let (mut left, rest) = self.split_at(len - 1);
+                let id = left.segments.last().or_else(|| rest.segments.first()).map(|s| s.id);
+                if let Some(id) = id {
+                    left.push(Segment::new_id(id, ELLIPSIS.to_owned()));
+                }
+                left
+            }
+        }
+    }
+    pub fn split_at(self, col: usize) -> (Self, Self) {
+        let mut left = Self::default();
+        let mut right = Self::default();
+        for segment in self.segments {
+            if left.len >= col {
+                right.push(segment);
+            } else if left.len + segment.len <= col {
+                left.push(segment);
+            } else {
+                let (a, b) = segment.split_at(col - left.len);
+                left.push(a);
+                right.push(b);
+            }
+        }
+        (left, right)
+    }
+    // Breaks the line into rows no wider than `width`, keeping segment boundaries intact.
+    // Rows may come up short of `width`, so callers should pad them.
+    pub fn wrap(self, width: usize, wrap: Wrap) -> Vec<Self> {
+        let indent_id = match self.segments.first() {
+            Some(segment) if width > 0 => segment.id,
+            _ => return vec![],
+        };
+        let indent = if wrap.indent < width { wrap.indent } else { 0 };
+        let word_mode = wrap.mode == WrapMode::Word;
+        let text: String = self.segments.iter().map(|s| s.text.as_str()).collect();
+        let mut cells = vec![];
+        let mut offset = 0;
+        for (seg_idx, segment) in self.segments.iter().enumerate() {
+            let graphemes = UnicodeSegmentation::grapheme_indices(segment.text.as_str(), true);
+            for (idx, grapheme) in graphemes {
+                cells.push(WrapCell {
+                    segment: seg_idx,
+                    start: offset + idx,
+                    end: offset + idx + grapheme.len(),
+                    width: grapheme_width(grapheme),
+                    space: grapheme.chars().all(char::is_whitespace),
+                });
+            }
+            offset += segment.text.len();
+        }
+        // breaks[i] says whether a row may (Some(false)) or must (Some(true)) start at cells[i]
+        let mut breaks: Vec<Option<bool>> = vec![None; cells.len() + 1];
+        if word_mode {
+            for (byte, opportunity) in linebreaks(&text) {
+                if let Ok(i) = cells.binary_search_by_key(&byte, |c| c.start) {
+                    breaks[i] = Some(opportunity == BreakOpportunity::Mandatory);
+                }
+            }
+        } else {
+            for b in &mut breaks {
+                *b = Some(false);
+            }
+        }
+
+        let mut rows = vec![];
+        let mut start = 0;
+        while start < cells.len() {
+            let avail = if rows.is_empty() { width } else { width - indent };
+            let mut used = 0;
+            let mut end = start;
+            let mut last_break = None;
+            let mut forced = false;
+            while end < cells.len() {
+                if end > start && breaks[end] == Some(true) {
+                    forced = true;
+                    break;
+                }
+                if end > start && breaks[end].is_some() {
+                    last_break = Some(end);
+                }
+                if used + cells[end].width > avail {
+                    if let (true, false, Some(b)) = (word_mode, cells[end].space, last_break) {
+                        end = b;
                     }
-                }).collect();
-            self.len = len;
+                    break;
+                }
+                used += cells[end].width;
+                end += 1;
+            }
+            let mut next = end;
+            if end == start && !forced {
+                // A lone cell wider than the row can never be shown
+                next += 1;
+            }
+            if word_mode {
+                while end > start && cells[end - 1].space {
+                    end -= 1;
+                }
+                if !forced {
+                    while next < cells.len() && cells[next].space {
+                        next += 1;
+                    }
+                }
+            }
+            let mut row = Self::default();
+            if !rows.is_empty() && indent > 0 {
+                row.push(Segment::blank_sized(indent_id, indent));
+            }
+            for (seg_idx, group) in &cells[start..end].iter().group_by(|c| c.segment) {
+                let group: Vec<&WrapCell> = group.collect();
+                let (first, last) = (group[0], group[group.len() - 1]);
+                let source = &self.segments[seg_idx];
+                let text = text[first.start..last.end].to_owned();
+                let mut segment = Segment::new_id(source.id, text);
+                segment.link = source.link.clone();
+                row.push(segment);
+            }
+            rows.push(row);
+            start = next;
         }
-        self
+        rows
     }
     pub fn push(&mut self, segment: Segment<N>) {
         self.len += segment.len;
@@ -116,6 +395,139 @@ impl<N: Name> TextLine<N> {
     }
 }
 
+struct WrapCell {
+    segment: usize,
+    start: usize,
+    end: usize,
+    width: usize,
+    space: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum WrapMode {
+    // Fill every row, breaking anywhere
+    Grapheme,
+    // Break at Unicode line break opportunities, falling back to Grapheme for overlong words
+    #[default]
+    Word,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Wrap {
+    pub mode: WrapMode,
+    // Leading blank columns on every row after the first
+    pub indent: usize,
+}
+
+impl Wrap {
+    pub fn new(mode: WrapMode, indent: usize) -> Self {
+        Self { mode, indent }
+    }
+}
+
+const ELLIPSIS: &str = "…";
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Overflow {
+    #[default]
+    Cut,
+    // Replace the last visible column with an ellipsis
+    Ellipsis,
+    // Keep both ends and elide the middle, which suits paths
+    MiddleEllipsis,
+}
+
+fn pad_line<N: Name>(
+    id: ContentID<N>,
+    line: TextLine<N>,
+    width: usize,
+    align: Align,
+) -> TextLine<N> {
+    if line.len >= width {
+        return line;
+    }
+    let extra = width - line.len;
+    let before = match align {
+        Align::Left => 0,
+        Align::Center => extra / 2,
+        Align::Right => extra,
+    };
+    let mut padded = TextLine::default();
+    if before > 0 {
+        padded.push(Segment::blank_sized(id, before));
+    }
+    padded.hconcat(line);
+    if extra > before {
+        padded.push(Segment::blank_sized(id, extra - before));
+    }
+    padded
+}
+
+// A single line of text made up of differently classed spans
+#[derive(Debug, Clone, Default)]
+pub struct RichLine {
+    spans: Vec<(&'static str, String, Option<String>)>,
+}
+
+impl RichLine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn span<S: Into<String>>(mut self, class: &'static str, text: S) -> Self {
+        self.spans.push((class, text.into(), None));
+        self
+    }
+    pub fn link<S: Into<String>, T: Into<String>>(
+        mut self,
+        class: &'static str,
+        text: S,
+        target: T,
+    ) -> Self {
+        self.spans.push((class, text.into(), Some(target.into())));
+        self
+    }
+    // The width into_text_line will give the line, with tabs expanded and control characters
+    // escaped
+    pub fn width(&self, tab_width: usize) -> usize {
+        self.spans.iter().fold(0, |col, (_, text, _)| {
+            col + text_width(&sanitize(text, col, tab_width))
+        })
+    }
+    pub fn into_text_line<N: Name>(self, id: ContentID<N>, tab_width: usize) -> TextLine<N> {
+        let mut line = TextLine::default();
+        for (class, text, link) in self.spans {
+            let id = ContentID { class, ..id };
+            let segment = Segment::new_at(id, text, line.len, tab_width);
+            line.push(match link {
+                Some(target) => segment.with_link(target),
+                None => segment,
+            });
+        }
+        line
+    }
+}
+
+impl<S: Into<String>> From<(&'static str, S)> for RichLine {
+    fn from((class, text): (&'static str, S)) -> Self {
+        RichLine::new().span(class, text)
+    }
+}
+
+impl<N: Name> Default for TextLine<N> {
+    fn default() -> Self {
+        let segments = vec![];
+        Self { segments, len: 0 }
+    }
+}
+
 impl<N: Name> From<Segment<N>> for TextLine<N> {
     fn from(segment: Segment<N>) -> Self {
         let len = segment.len;
@@ -128,9 +540,21 @@ pub struct TextBlock<N: Name> {
     pub lines: Vec<TextLine<N>>,
     pub size: Size,
     key_callbacks: IndexTree<N, KeyCallback<N>>,
-    cursors: HashMap<N, Pos>,
+    cursors: HashMap<N, (Pos, CursorStyle)>,
     hit_map: Vec<Vec<Option<usize>>>,
-    mouse_callbacks: IndexTree<usize, (Option<N>, Pos, MouseCallback<N>)>,
+    // Each handler keeps the origin its positions are relative to and the region it covers
+    mouse_callbacks: IndexTree<usize, (Option<N>, Pos, Rect, MouseHandler<N>)>,
+    // Names that can take focus, in the order they were rendered
+    focus_order: Vec<N>,
+    focus_disabled: HashSet<N>,
+    // Names that dropped out of the focus order because something was painted over them
+    covered: HashSet<N>,
+    focus_callbacks: Vec<(N, FocusCallback<N>)>,
+}
+
+enum MouseHandler<N: Name> {
+    Click(MouseCallback<N>),
+    Hover(HoverCallback<N>),
 }
 
 impl<N: Name> TextBlock<N> {
@@ -147,11 +571,15 @@ impl<N: Name> TextBlock<N> {
             cursors: HashMap::new(),
             hit_map,
             mouse_callbacks: IndexTree::new(),
+            focus_order: Vec::new(),
+            focus_disabled: HashSet::new(),
+            covered: HashSet::new(),
+            focus_callbacks: Vec::new(),
             lines,
             size,
         }
     }
-    pub fn handle_key(&self, event_ctx: &EventContext<N>, focus: &N, key: Key) {
+    pub fn handle_key(&self, event_ctx: &EventContext<N>, focus: &N, key: KeyEvent) {
         use ShouldPropagate::*;
         for cb in self.key_callbacks.get_iter(focus) {
             match cb(event_ctx, key) {
@@ -160,52 +588,186 @@ impl<N: Name> TextBlock<N> {
             }
         }
     }
-    // XXX TODO Need to use internal mouse event type instead of termion's, with relative coords
+    // Tells the handlers registered for the name that it gained or lost focus, returning
+    // whether there were any
+    pub fn handle_focus(&self, event_ctx: &EventContext<N>, name: N, event: FocusEvent) -> bool {
+        let mut handled = false;
+        for (_, cb) in self.focus_callbacks.iter().filter(|(n, _)| *n == name) {
+            cb(event_ctx, event);
+            handled = true;
+        }
+        handled
+    }
     pub fn handle_mouse(&self, event_ctx: &EventContext<N>, mevent: MouseEvent) {
         use ShouldPropagate::*;
-        let (x, y) = match mevent {
-            MouseEvent::Press(_, x, y) => (x as usize - 1, y as usize - 1),
-            MouseEvent::Release(x, y) => (x as usize - 1, y as usize - 1),
-            MouseEvent::Hold(x, y) => (x as usize - 1, y as usize - 1),
-        };
-        if let Some(idx) = self.hit_map[y][x] {
-            let frame_pos = Pos::new(x, y);
-            for (_name, pos, cb) in self.mouse_callbacks.get_iter_idx(idx) {
-                match cb(event_ctx, frame_pos - *pos, mevent) {
+        for (_name, pos, _rect, handler) in self.handlers_at(mevent.pos) {
+            if let MouseHandler::Click(cb) = handler {
+                match cb(event_ctx, mevent.relative_to(*pos)) {
                     Stop => break,
                     Continue => continue,
                 }
             }
         }
     }
+    fn handlers_at<'a>(
+        &'a self,
+        frame_pos: Pos,
+    ) -> impl Iterator<Item = &'a (Option<N>, Pos, Rect, MouseHandler<N>)> + 'a {
+        let idx = self
+            .hit_map
+            .get(frame_pos.row)
+            .and_then(|row| row.get(frame_pos.col))
+            .cloned()
+            .and_then(|idx| idx);
+        idx.into_iter()
+            .flat_map(move |idx| self.mouse_callbacks.get_iter_idx(idx))
+            .filter(move |(_, _, rect, _)| rect.contains(frame_pos))
+    }
+    // Moves the pointer to `frame_pos`, given the names that were hovered before, and sends
+    // Leave, Enter and Move to the hover handlers that need them. Returns the names now hovered.
+    pub fn handle_hover(
+        &self,
+        event_ctx: &EventContext<N>,
+        hovered: &[N],
+        frame_pos: Pos,
+    ) -> Vec<N> {
+        let mut now = vec![];
+        let mut entered = vec![];
+        for (name, pos, _rect, handler) in self.handlers_at(frame_pos) {
+            if let (Some(name), MouseHandler::Hover(cb)) = (name, handler) {
+                if now.contains(name) {
+                    continue;
+                }
+                now.push(*name);
+                entered.push((cb, frame_pos - *pos, hovered.contains(name)));
+            }
+        }
+        for name in hovered.iter().filter(|n| !now.contains(n)) {
+            let leaving = self.mouse_callbacks.iter_all().filter_map(|entry| match entry {
+                (Some(n), _, _, MouseHandler::Hover(cb)) if n == name => Some(cb),
+                _ => None,
+            });
+            for cb in leaving {
+                cb(event_ctx, HoverEvent::Leave);
+            }
+        }
+        for (cb, rel_pos, was_hovered) in entered {
+            let event = if was_hovered {
+                HoverEvent::Move(rel_pos)
+            } else {
+                HoverEvent::Enter(rel_pos)
+            };
+            if let ShouldPropagate::Stop = cb(event_ctx, event) {
+                break;
+            }
+        }
+        now
+    }
     pub fn clip_lines(id: ContentID<N>, lines: Vec<String>, bound: RenderBound) -> Self {
+        Self::clip_lines_with(id, lines, bound, Align::default(), Overflow::default())
+    }
+    pub fn wrap_lines(id: ContentID<N>, lines: Vec<String>, bound: RenderBound) -> Self {
+        Self::wrap_lines_with(id, lines, bound, Wrap::default(), Align::default())
+    }
+    pub fn clip_rich(id: ContentID<N>, lines: Vec<RichLine>, bound: RenderBound) -> Self {
+        Self::clip_rich_with(id, lines, bound, Align::default(), Overflow::default())
+    }
+    pub fn wrap_rich(id: ContentID<N>, lines: Vec<RichLine>, bound: RenderBound) -> Self {
+        Self::wrap_rich_with(id, lines, bound, Wrap::default(), Align::default())
+    }
+    // `id` is used for any filler needed to pad out the bound
+    pub fn clip_text_lines(id: ContentID<N>, lines: Vec<TextLine<N>>, bound: RenderBound) -> Self {
+        Self::clip_text_lines_with(id, lines, bound, Align::default(), Overflow::default())
+    }
+    pub fn wrap_text_lines(id: ContentID<N>, lines: Vec<TextLine<N>>, bound: RenderBound) -> Self {
+        Self::wrap_text_lines_with(id, lines, bound, Wrap::default(), Align::default())
+    }
+    // The same, laid out with the given alignment, overflow and wrapping
+    pub fn clip_lines_with(
+        id: ContentID<N>,
+        lines: Vec<String>,
+        bound: RenderBound,
+        align: Align,
+        overflow: Overflow,
+    ) -> Self {
+        let lines = lines
+            .into_iter()
+            .map(|l| Segment::new_id(id, l).into())
+            .collect();
+        Self::clip_text_lines_with(id, lines, bound, align, overflow)
+    }
+    pub fn wrap_lines_with(
+        id: ContentID<N>,
+        lines: Vec<String>,
+        bound: RenderBound,
+        wrap: Wrap,
+        align: Align,
+    ) -> Self {
+        let lines = lines
+            .into_iter()
+            .map(|l| Segment::new_id(id, l).into())
+            .collect();
+        Self::wrap_text_lines_with(id, lines, bound, wrap, align)
+    }
+    pub fn clip_rich_with(
+        id: ContentID<N>,
+        lines: Vec<RichLine>,
+        bound: RenderBound,
+        align: Align,
+        overflow: Overflow,
+    ) -> Self {
+        let lines = lines.into_iter().map(|l| l.into_text_line(id, DEFAULT_TAB_WIDTH)).collect();
+        Self::clip_text_lines_with(id, lines, bound, align, overflow)
+    }
+    pub fn wrap_rich_with(
+        id: ContentID<N>,
+        lines: Vec<RichLine>,
+        bound: RenderBound,
+        wrap: Wrap,
+        align: Align,
+    ) -> Self {
+        let lines = lines.into_iter().map(|l| l.into_text_line(id, DEFAULT_TAB_WIDTH)).collect();
+        Self::wrap_text_lines_with(id, lines, bound, wrap, align)
+    }
+    pub fn clip_text_lines_with(
+        id: ContentID<N>,
+        lines: Vec<TextLine<N>>,
+        bound: RenderBound,
+        align: Align,
+        overflow: Overflow,
+    ) -> Self {
         let width = match bound.width {
             Some(width) => width,
-            None => lines.iter().map(|l| l.len()).max().unwrap_or(0),
+            None => lines.iter().map(|l| l.len).max().unwrap_or(0),
         };
-        let tl_iter = lines.into_iter().map(|l| {
-            let tl: TextLine<N> = Segment::new_id(id, l).into();
-            tl.clip(width)
-        });
-        let lines: Vec<TextLine<N>> = match bound.height {
-            Some(height) => tl_iter
-                .chain(repeat(Segment::blank_sized(id, width).into()))
-                .take(height)
-                .collect(),
-            None => tl_iter.collect(),
-        };
-        let height = lines.len();
-        Self::new(lines, width, height)
+        let tl_iter = lines
+            .into_iter()
+            .map(|tl| pad_line(id, tl.truncate(width, overflow), width, align));
+        Self::fill_height(id, tl_iter, width, bound)
     }
-    pub fn wrap_lines(id: ContentID<N>, lines: Vec<String>, bound: RenderBound) -> Self {
+    pub fn wrap_text_lines_with(
+        id: ContentID<N>,
+        lines: Vec<TextLine<N>>,
+        bound: RenderBound,
+        wrap: Wrap,
+        align: Align,
+    ) -> Self {
         let width = match bound.width {
             Some(width) => width,
-            None => lines.iter().map(|l| l.len()).max().unwrap_or(0),
+            None => lines.iter().map(|l| l.len).max().unwrap_or(0),
         };
         let tl_iter = lines
             .into_iter()
-            .flat_map(|l| split_line_graphemes(&l, width).into_iter())
-            .map(|l| Segment::new_id(id, l).into());
+            .flat_map(|tl| tl.wrap(width, wrap).into_iter())
+            .map(|tl| pad_line(id, tl, width, align));
+        Self::fill_height(id, tl_iter, width, bound)
+    }
+    fn fill_height(
+        id: ContentID<N>,
+        tl_iter: impl Iterator<Item = TextLine<N>>,
+        width: usize,
+        bound: RenderBound,
+    ) -> Self {
         let lines: Vec<TextLine<N>> = match bound.height {
             Some(height) => tl_iter
                 .chain(repeat(Segment::blank_sized(id, width).into()))
@@ -220,28 +782,97 @@ impl<N: Name> TextBlock<N> {
         self.size
     }
     pub fn add_key_handler(mut self, name: Option<N>, callback: KeyCallback<N>) -> Self {
+        if let Some(name) = name {
+            self.add_focusable(name);
+        }
         self.key_callbacks.push(name, callback);
         self
     }
-    pub fn add_mouse_handler(mut self, name: Option<N>, callback: MouseCallback<N>) -> Self {
+    pub fn add_mouse_handler(self, name: Option<N>, callback: MouseCallback<N>) -> Self {
+        let rect = Rect::new(Pos::new(0, 0), self.size);
+        self.add_mouse_handler_rect(name, rect, callback)
+    }
+    // Only clicks inside `rect` reach the handler, though positions stay relative to the block
+    pub fn add_mouse_handler_rect(
+        self,
+        name: Option<N>,
+        rect: Rect,
+        callback: MouseCallback<N>,
+    ) -> Self {
+        self.push_mouse_handler(name, rect, MouseHandler::Click(callback))
+    }
+    pub fn add_hover_handler(self, name: N, rect: Rect, callback: HoverCallback<N>) -> Self {
+        self.push_mouse_handler(Some(name), rect, MouseHandler::Hover(callback))
+    }
+    fn push_mouse_handler(mut self, name: Option<N>, rect: Rect, handler: MouseHandler<N>) -> Self {
         let idx = self
             .mouse_callbacks
-            .push(None, (name, Pos::new(0, 0), callback));
-        for row in &mut self.hit_map {
-            for cell in row {
-                if cell.is_none() {
+            .push(None, (name, Pos::new(0, 0), rect, handler));
+        for (row_idx, row) in self.hit_map.iter_mut().enumerate() {
+            for (col_idx, cell) in row.iter_mut().enumerate() {
+                if cell.is_none() && rect.contains(Pos::new(col_idx, row_idx)) {
                     *cell = Some(idx);
                 }
             }
         }
         self
     }
-    pub fn add_cursor(mut self, name: N, pos: Pos) -> Self {
-        self.cursors.insert(name, pos);
+    pub fn add_cursor(self, name: N, pos: Pos) -> Self {
+        self.add_styled_cursor(name, pos, CursorStyle::default())
+    }
+    pub fn add_styled_cursor(mut self, name: N, pos: Pos, style: CursorStyle) -> Self {
+        self.add_focusable(name);
+        self.cursors.insert(name, (pos, style));
         self
     }
+    pub fn add_focus_handler(mut self, name: N, callback: FocusCallback<N>) -> Self {
+        self.focus_callbacks.push((name, callback));
+        self
+    }
+    fn add_focusable(&mut self, name: N) {
+        if !self.focus_order.contains(&name) {
+            self.focus_order.push(name);
+        }
+    }
+    // Keeps the name's handlers and cursor, but focus traversal will skip over it
+    pub fn disable_focus(mut self, name: N) -> Self {
+        self.focus_disabled.insert(name);
+        self
+    }
+    // Whether the name was rendered able to take focus, but something like a modal was painted
+    // over it, so focus left on it has to move
+    pub fn is_covered(&self, name: &N) -> bool {
+        self.covered.contains(name) && !self.focus_order.contains(name)
+    }
+    pub fn focus_order(&self) -> Vec<N> {
+        self.focus_order
+            .iter()
+            .filter(|name| !self.focus_disabled.contains(name))
+            .cloned()
+            .collect()
+    }
+    // Both wrap around, and start from either end if `current` isn't focusable
+    pub fn next_focus(&self, current: &N) -> Option<N> {
+        let order = self.focus_order();
+        let next = match order.iter().position(|name| name == current) {
+            Some(idx) => order.get(idx + 1).or_else(|| order.first()),
+            None => order.first(),
+        };
+        next.cloned()
+    }
+    pub fn prev_focus(&self, current: &N) -> Option<N> {
+        let order = self.focus_order();
+        let prev = match order.iter().position(|name| name == current) {
+            Some(0) | None => order.last(),
+            Some(idx) => order.get(idx - 1),
+        };
+        prev.cloned()
+    }
     pub fn get_cursor(&self, name: N) -> Option<Pos> {
-        self.cursors.get(&name).cloned()
+        self.cursors.get(&name).map(|c| c.0)
+    }
+    pub fn get_cursor_style(&self, name: N) -> Option<CursorStyle> {
+        self.cursors.get(&name).map(|c| c.1)
     }
     pub fn trim_top(mut self, mut trim_lines: usize) -> Self {
         if trim_lines >= self.size.rows {
@@ -249,11 +880,15 @@ impl<N: Name> TextBlock<N> {
         }
         self.lines = self.lines.split_off(trim_lines);
         self.hit_map = self.hit_map.split_off(trim_lines);
-        for (_name, mut pos, _cb) in self.mouse_callbacks.iter_all_mut() {
-            pos -= Pos::new(0, trim_lines)
+        // Handlers scrolled partly off the top keep their origin pinned at the edge
+        for (_name, pos, rect, _cb) in self.mouse_callbacks.iter_all_mut() {
+            pos.row = pos.row.saturating_sub(trim_lines);
+            let bottom = rect.pos.row + rect.size.rows;
+            rect.pos.row = rect.pos.row.saturating_sub(trim_lines);
+            rect.size.rows = bottom.saturating_sub(trim_lines) - rect.pos.row;
         }
         // XXX TODO Probably best to remove these entirely if they're in the trimmed region
-        for mut pos in self.cursors.values_mut() {
+        for (pos, _style) in self.cursors.values_mut() {
             *pos -= Pos::new(0, trim_lines)
         }
         self.size.rows -= trim_lines;
@@ -270,63 +905,163 @@ impl<N: Name> TextBlock<N> {
         self.size.rows -= trim_lines;
         self
     }
-    // Maybe factor out the common parts of these?
-    pub fn vconcat(mut self, mut other: Self) -> Self {
-        assert_eq!(self.size.cols, other.size.cols); // XXX TODO Maybe expand the smaller to fit?
-        let pos_offset = Pos::new(0, self.size.rows);
-        self.lines.append(&mut other.lines);
-
-        self.key_callbacks.append(&mut other.key_callbacks);
+    pub fn trim_right(mut self, mut trim_cols: usize) -> Self {
+        if trim_cols >= self.size.cols {
+            trim_cols = self.size.cols;
+        }
+        let trim_idx = self.size.cols - trim_cols;
+        self.lines = self
+            .lines
+            .into_iter()
+            .map(|l| l.split_at(trim_idx).0)
+            .collect();
+        for row in &mut self.hit_map {
+            row.truncate(trim_idx);
+        }
+        self.size.cols -= trim_cols;
+        self
+    }
+    // Takes over other's handlers and cursors, shifted by offset, and hands back the parts
+    // that need to be placed: its lines and its hit map re-indexed into our mouse callbacks
+    fn absorb(
+        &mut self,
+        other: Self,
+        pos_offset: Pos,
+    ) -> (Vec<TextLine<N>>, Vec<Vec<Option<usize>>>) {
+        let TextBlock {
+            lines,
+            mut key_callbacks,
+            cursors,
+            mut hit_map,
+            mouse_callbacks,
+            focus_order,
+            focus_disabled,
+            covered,
+            focus_callbacks,
+            ..
+        } = other;
+        self.focus_callbacks.extend(focus_callbacks);
+        self.key_callbacks.append(&mut key_callbacks);
+        for name in focus_order {
+            self.add_focusable(name);
+        }
+        self.focus_disabled.extend(focus_disabled);
+        self.covered.extend(covered);
 
-        let mut offset_mouse_callbacks = other
-            .mouse_callbacks
-            .map(|(name, pos, cb)| (name, pos + pos_offset, cb));
+        let mut offset_mouse_callbacks = mouse_callbacks.map(|(name, pos, rect, cb)| {
+            let rect = Rect::new(rect.pos + pos_offset, rect.size);
+            (name, pos + pos_offset, rect, cb)
+        });
         let idx_offset = self.mouse_callbacks.append(&mut offset_mouse_callbacks);
-        for row in &mut other.hit_map {
+        for row in &mut hit_map {
             for cell in row {
                 *cell = cell.map(|i| i + idx_offset);
             }
         }
-        self.hit_map.append(&mut other.hit_map);
 
         self.cursors.extend(
-            other
-                .cursors
+            cursors
                 .into_iter()
-                .map(move |(n, p)| (n, p + pos_offset)),
+                .map(move |(n, (p, style))| (n, (p + pos_offset, style))),
         );
-        self.size.rows += other.size.rows;
+        (lines, hit_map)
+    }
+    pub fn vconcat(mut self, other: Self) -> Self {
+        assert_eq!(self.size.cols, other.size.cols); // XXX TODO Maybe expand the smaller to fit?
+        let pos_offset = Pos::new(0, self.size.rows);
+        let rows = other.size.rows;
+        let (mut lines, mut hit_map) = self.absorb(other, pos_offset);
+        self.lines.append(&mut lines);
+        self.hit_map.append(&mut hit_map);
+        self.size.rows += rows;
         self
     }
-    pub fn hconcat(mut self, mut other: Self) -> Self {
+    pub fn hconcat(mut self, other: Self) -> Self {
         assert_eq!(self.size.rows, other.size.rows); // XXX TODO Maybe expand the smaller to fit?
-        let pos_offset = Pos::new(self.size.rows, 0);
-        for (mut a, b) in self.lines.iter_mut().zip(other.lines.into_iter()) {
+        let pos_offset = Pos::new(self.size.cols, 0);
+        let cols = other.size.cols;
+        let (lines, hit_map) = self.absorb(other, pos_offset);
+        for (a, b) in self.lines.iter_mut().zip(lines.into_iter()) {
             a.hconcat(b);
         }
-        self.key_callbacks.append(&mut other.key_callbacks);
-
-        let mut offset_mouse_callbacks = other
-            .mouse_callbacks
-            .map(|(name, pos, cb)| (name, pos + pos_offset, cb));
-        let idx_offset = self.mouse_callbacks.append(&mut offset_mouse_callbacks);
-        for row in &mut other.hit_map {
+        for (a, b) in self.hit_map.iter_mut().zip(hit_map.into_iter()) {
+            a.extend_from_slice(&b)
+        }
+        self.size.cols += cols;
+        self
+    }
+    // Paints other on top of self with its top left corner at `at`, cropping whatever hangs
+    // off the edge. The covered region takes other's text, mouse hits and cursors, and other's
+    // key handlers take precedence for any names the two share. Names left entirely under
+    // other, by their cursor or their mouse handlers, drop out of the focus order.
+    pub fn overlay(mut self, other: Self, at: Pos) -> Self {
+        if at.row >= self.size.rows || at.col >= self.size.cols {
+            return self;
+        }
+        let extra_rows = (at.row + other.size.rows).saturating_sub(self.size.rows);
+        let extra_cols = (at.col + other.size.cols).saturating_sub(self.size.cols);
+        let other = other.trim_bottom(extra_rows).trim_right(extra_cols);
+        let region = Rect::new(at, other.size);
+        let mut covered: Vec<N> = vec![];
+        for name in &self.focus_order {
+            let hidden = match self.cursors.get(name) {
+                Some((pos, _)) => region.contains(*pos),
+                None => {
+                    let mut rects = self
+                        .mouse_callbacks
+                        .iter_all()
+                        .filter(|(n, _, _, _)| *n == Some(*name))
+                        .map(|(_, _, rect, _)| rect)
+                        .peekable();
+                    rects.peek().is_some() && rects.all(|rect| region.covers(*rect))
+                }
+            };
+            if hidden {
+                covered.push(*name);
+            }
+        }
+        self.cursors.retain(|_, (p, _)| !region.contains(*p));
+        self.focus_order.retain(|name| !covered.contains(name));
+        self.covered.extend(covered);
+        let size = other.size;
+        let (lines, hit_map) = self.absorb(other, at);
+        for (row, (line, hits)) in (at.row..).zip(lines.into_iter().zip(hit_map)) {
+            let base = mem::take(&mut self.lines[row]);
+            let (mut left, rest) = base.split_at(at.col);
+            let (_, right) = rest.split_at(size.cols);
+            left.hconcat(line);
+            left.hconcat(right);
+            self.lines[row] = left;
+            self.hit_map[row][at.col..at.col + size.cols].copy_from_slice(&hits);
+        }
+        self
+    }
+    // Run once the whole UI is composed, since lines only meet once they are side by side
+    pub fn join_lines(mut self) -> Self {
+        join_lines(&mut self.lines);
+        self
+    }
+    // Drops the key and mouse handlers, cursors and focus, leaving just the text. Names that
+    // could take focus count as covered.
+    pub fn without_input(mut self) -> Self {
+        self.key_callbacks = IndexTree::new();
+        self.cursors.clear();
+        for row in &mut self.hit_map {
             for cell in row {
-                *cell = cell.map(|i| i + idx_offset);
+                *cell = None;
             }
         }
-
-        for (mut a, b) in self.hit_map.iter_mut().zip(other.hit_map.into_iter()) {
-            a.extend_from_slice(&b)
+        self.mouse_callbacks = IndexTree::new();
+        self.covered.extend(self.focus_order.drain(..));
+        self.focus_disabled.clear();
+        self.focus_callbacks.clear();
+        self
+    }
+    // Gives all of the text `class`, keeping the names and widget types it was rendered with
+    pub fn with_class(mut self, class: &'static str) -> Self {
+        for seg in self.lines.iter_mut().flat_map(|l| l.segments.iter_mut()) {
+            seg.id.class = class;
         }
-
-        self.cursors.extend(
-            other
-                .cursors
-                .into_iter()
-                .map(move |(n, p)| (n, p + pos_offset)),
-        );
-        self.size.cols += other.size.cols;
         self
     }
     pub fn concat_dir(self, direction: Direction, other: Self) -> Self {
@@ -337,7 +1072,7 @@ impl<N: Name> TextBlock<N> {
     }
     pub fn render_frame(&self, app: &App<N>, focus_name: Option<N>) -> Frame {
         let size = self.size;
-        let focus = focus_name.and_then(|name| self.get_cursor(name));
+        let focus = focus_name.and_then(|name| self.cursors.get(&name).cloned());
         let image: Vec<FrameLine> = self
             .lines
             .iter()
@@ -345,10 +1080,10 @@ impl<N: Name> TextBlock<N> {
                 tl.segments
                     .iter()
                     .map(|seg| {
-                        let (mfg, mbg) = app.style(seg.id);
-                        let fg = mfg.unwrap_or(Color::Reset);
-                        let bg = mbg.unwrap_or(Color::Reset);
-                        (fg, bg, seg.text.as_ref(), seg.len)
+                        let link = seg.link.as_deref();
+                        let focused = seg.id.name.is_some() && seg.id.name == focus_name;
+                        let id = ContentID { focused, ..seg.id };
+                        (app.style(id), seg.text.as_ref(), seg.len, link)
                     }).collect()
             }).collect();
         Frame::new(size, image, focus)
@@ -368,16 +1103,328 @@ where
     }
 }
 
-pub type FrameLine<'a> = Vec<(Color, Color, &'a str, usize)>;
+pub type FrameLine<'a> = Vec<(Style, &'a str, usize, Option<&'a str>)>;
 
 pub struct Frame<'a> {
     pub size: Size,
     pub image: Vec<FrameLine<'a>>,
-    pub focus: Option<Pos>,
+    pub focus: Option<(Pos, CursorStyle)>,
 }
 
 impl<'a> Frame<'a> {
-    pub fn new(size: Size, image: Vec<FrameLine<'a>>, focus: Option<Pos>) -> Self {
+    pub fn new(size: Size, image: Vec<FrameLine<'a>>, focus: Option<(Pos, CursorStyle)>) -> Self {
         Self { size, image, focus }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        sanitize, split_line_graphemes, text_width, Align, Overflow, RichLine, Segment, TextBlock,
+        TextLine, Wrap, WrapMode,
+    };
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use test_util::{event_context, row_text};
+    use {
+        ContentID, FocusCallback, FocusEvent, HoverEvent, KeyCallback, Pos, Rect, RenderBound,
+        ShouldPropagate, Size,
+    };
+
+    fn id() -> ContentID<u8> {
+        ContentID::new(None, "Test", "Test")
+    }
+
+    #[test]
+    fn widths() {
+        assert_eq!(text_width("abc"), 3);
+        assert_eq!(text_width("日本"), 4);
+        assert_eq!(text_width("e\u{301}"), 1);
+        assert_eq!(text_width("a🦀b"), 4);
+        assert_eq!(text_width("👩\u{200d}👩\u{200d}👧"), 2);
+    }
+
+    #[test]
+    fn segment_len() {
+        let seg: Segment<u8> = Segment::new_id(id(), "ab日本".to_owned());
+        assert_eq!(seg.len, 6);
+    }
+
+    #[test]
+    fn clip_mixed_width() {
+        let tl: TextLine<u8> = Segment::new_id(id(), "a日本b".to_owned()).into();
+        let clipped = tl.clone().clip(4);
+        assert_eq!(clipped.len, 4);
+        assert_eq!(clipped.segments[0].text, "a日 ");
+        let clipped = tl.clone().clip(3);
+        assert_eq!(clipped.segments[0].text, "a日");
+        let padded = tl.clip(8);
+        assert_eq!(padded.len, 8);
+        assert_eq!(text_width(&padded.segments[1].text), 2);
+    }
+
+    #[test]
+    fn wrap_mixed_width() {
+        assert_eq!(split_line_graphemes("ab日本c", 3), vec!["ab ", "日 ", "本c"]);
+        assert_eq!(split_line_graphemes("日x", 1), vec![" ", "x"]);
+        assert_eq!(split_line_graphemes("a日本", 1), vec!["a", " ", " "]);
+        assert!(split_line_graphemes("", 3).is_empty());
+    }
+
+    fn block(lines: &[&str]) -> TextBlock<u8> {
+        let lines = lines.iter().map(|l| l.to_string()).collect();
+        let bound = RenderBound::new(None, None);
+        TextBlock::clip_lines(id(), lines, bound)
+    }
+
+    #[test]
+    fn overlay_paints_and_crops() {
+        let base = block(&["abcd", "efgh", "ijkl"]).add_cursor(1, Pos::new(1, 1));
+        let popup = block(&["XY", "ZW"]).add_cursor(2, Pos::new(0, 0));
+        let out = base.overlay(popup, Pos::new(1, 1));
+        assert_eq!(row_text(&out, 0), "abcd");
+        assert_eq!(row_text(&out, 1), "eXYh");
+        assert_eq!(row_text(&out, 2), "iZWl");
+        assert!(out.get_cursor(1).is_none());
+        assert_eq!(out.get_cursor(2).map(|p| (p.col, p.row)), Some((1, 1)));
+
+        let edge = block(&["XYZ"]);
+        let out = block(&["abcd"]).overlay(edge, Pos::new(2, 0));
+        assert_eq!(row_text(&out, 0), "abXY");
+        assert_eq!(out.size().cols, 4);
+    }
+
+    #[test]
+    fn overlay_takes_mouse_hits() {
+        let base = block(&["abcd"])
+            .add_mouse_handler(Some(1), Box::new(|_, _| ShouldPropagate::Stop));
+        let popup = block(&["XY"])
+            .add_mouse_handler(Some(2), Box::new(|_, _| ShouldPropagate::Stop));
+        let out = base.overlay(popup, Pos::new(1, 0));
+        let owners: Vec<Option<u8>> = out.hit_map[0]
+            .iter()
+            .map(|idx| {
+                idx.and_then(|i| out.mouse_callbacks.get_iter_idx(i).next())
+                    .and_then(|(name, _, _, _)| *name)
+            }).collect();
+        assert_eq!(owners, vec![Some(1), Some(2), Some(2), Some(1)]);
+    }
+
+    #[test]
+    fn overlay_focus_order() {
+        let noop = || -> KeyCallback<u8> { Box::new(|_, _| ShouldPropagate::Continue) };
+        let base = block(&["abcd"])
+            .add_cursor(1, Pos::new(0, 0))
+            .add_cursor(2, Pos::new(2, 0))
+            .add_key_handler(Some(3), noop())
+            .add_mouse_handler_rect(
+                Some(5),
+                Rect::new(Pos::new(3, 0), Size::new(1, 1)),
+                Box::new(|_, _| ShouldPropagate::Stop),
+            );
+        let popup = block(&["XY"]).add_cursor(4, Pos::new(0, 0));
+        let out = base.overlay(popup, Pos::new(2, 0));
+        assert_eq!(out.focus_order(), vec![1, 3, 4]);
+    }
+
+    #[test]
+    fn hconcat_offsets_by_columns() {
+        let left = block(&["ab", "cd", "ef"]);
+        let right = block(&["X", "Y", "Z"])
+            .add_cursor(1, Pos::new(0, 1))
+            .add_mouse_handler(Some(1), Box::new(|_, _| ShouldPropagate::Stop));
+        let out = left.hconcat(right);
+        assert_eq!(out.get_cursor(1), Some(Pos::new(2, 1)));
+        let (_, pos, rect, _) = out.mouse_callbacks.iter_all().next().unwrap();
+        assert_eq!(*pos, Pos::new(2, 0));
+        assert_eq!(rect.pos, Pos::new(2, 0));
+    }
+
+    #[test]
+    fn focus_traversal() {
+        let noop = || -> KeyCallback<u8> { Box::new(|_, _| ShouldPropagate::Continue) };
+        let top = block(&["ab"])
+            .add_key_handler(Some(1), noop())
+            .add_cursor(2, Pos::new(0, 0));
+        let bottom = block(&["cd"])
+            .add_key_handler(Some(3), noop())
+            .add_key_handler(Some(4), noop())
+            .disable_focus(3);
+        let out = top.vconcat(bottom);
+        assert_eq!(out.focus_order(), vec![1, 2, 4]);
+        assert_eq!(out.next_focus(&2), Some(4));
+        assert_eq!(out.next_focus(&4), Some(1));
+        assert_eq!(out.prev_focus(&1), Some(4));
+        assert_eq!(out.next_focus(&3), Some(1));
+    }
+
+    #[test]
+    fn focus_events() {
+        let seen = Rc::new(RefCell::new(vec![]));
+        let log = |name: u8| -> FocusCallback<u8> {
+            let seen = seen.clone();
+            Box::new(move |_, ev| seen.borrow_mut().push((name, ev)))
+        };
+        let ui = block(&["ab"])
+            .add_focus_handler(1, log(1))
+            .add_focus_handler(2, log(2));
+        let (ctx, _receiver) = event_context();
+        assert!(ui.handle_focus(&ctx, 1, FocusEvent::Gained));
+        assert!(ui.handle_focus(&ctx, 1, FocusEvent::Lost));
+        assert!(ui.handle_focus(&ctx, 2, FocusEvent::Gained));
+        assert!(!ui.handle_focus(&ctx, 3, FocusEvent::Gained));
+        assert_eq!(
+            *seen.borrow(),
+            vec![
+                (1, FocusEvent::Gained),
+                (1, FocusEvent::Lost),
+                (2, FocusEvent::Gained),
+            ]
+        );
+    }
+
+    #[test]
+    fn hover_tracks_rects() {
+        let seen = Rc::new(RefCell::new(vec![]));
+        let log = seen.clone();
+        let button = Rect::new(Pos::new(1, 0), Size::new(2, 1));
+        let block = block(&["abcd"]).add_hover_handler(
+            1,
+            button,
+            Box::new(move |_, ev| {
+                log.borrow_mut().push(ev);
+                ShouldPropagate::Stop
+            }),
+        );
+        let (ctx, _receiver) = event_context();
+        let hovered = block.handle_hover(&ctx, &[], Pos::new(0, 0));
+        assert!(hovered.is_empty());
+        let hovered = block.handle_hover(&ctx, &hovered, Pos::new(1, 0));
+        let hovered = block.handle_hover(&ctx, &hovered, Pos::new(2, 0));
+        assert_eq!(hovered, vec![1]);
+        let hovered = block.handle_hover(&ctx, &hovered, Pos::new(3, 0));
+        assert!(hovered.is_empty());
+        assert_eq!(
+            *seen.borrow(),
+            vec![
+                HoverEvent::Enter(Pos::new(1, 0)),
+                HoverEvent::Move(Pos::new(2, 0)),
+                HoverEvent::Leave,
+            ]
+        );
+    }
+
+    #[test]
+    fn rich_lines_keep_spans() {
+        let line = RichLine::new().span("Key", "key: ").span("Value", "value");
+        let bound = RenderBound::new(Some(12), None);
+        let block: TextBlock<u8> =
+            TextBlock::clip_rich(id(), vec![line], bound);
+        let classes: Vec<&str> = block.lines[0].segments.iter().map(|s| s.id.class).collect();
+        assert_eq!(classes, vec!["Key", "Value", "Test"]);
+        assert_eq!(row_text(&block, 0), "key: value  ");
+    }
+
+    #[test]
+    fn rich_wrap_splits_spans() {
+        let line = RichLine::new().span("Key", "key: ").span("Value", "日本語");
+        let bound = RenderBound::new(Some(6), None);
+        let wrap = Wrap::new(WrapMode::Grapheme, 0);
+        let block: TextBlock<u8> =
+            TextBlock::wrap_rich_with(id(), vec![line], bound, wrap, Align::Left);
+        assert_eq!(block.size().rows, 2);
+        assert_eq!(row_text(&block, 0), "key:  ");
+        assert_eq!(row_text(&block, 1), "日本語");
+        let row0: Vec<(&str, &str)> = block.lines[0]
+            .segments
+            .iter()
+            .map(|s| (s.id.class, s.text.as_str()))
+            .collect();
+        assert_eq!(row0, vec![("Key", "key: "), ("Test", " ")]);
+    }
+
+    fn wrapped(text: &str, width: usize, wrap: Wrap) -> Vec<String> {
+        let bound = RenderBound::new(Some(width), None);
+        let lines = vec![text.to_owned()];
+        let block: TextBlock<u8> =
+            TextBlock::wrap_lines_with(id(), lines, bound, wrap, Align::Left);
+        (0..block.size().rows).map(|r| row_text(&block, r)).collect()
+    }
+
+    #[test]
+    fn word_wrap() {
+        let wrap = Wrap::default();
+        assert_eq!(
+            wrapped("hello world foo", 8, wrap),
+            vec!["hello   ", "world   ", "foo     "]
+        );
+        assert_eq!(wrapped("hello world", 5, wrap), vec!["hello", "world"]);
+        assert_eq!(wrapped("abcdefghij xy", 4, wrap), vec!["abcd", "efgh", "ij  ", "xy  "]);
+        assert_eq!(wrapped("日本語です", 5, wrap), vec!["日本 ", "語で ", "す   "]);
+    }
+
+    #[test]
+    fn hanging_indent() {
+        let wrap = Wrap::new(WrapMode::Word, 2);
+        assert_eq!(
+            wrapped("aaa bbb ccc", 5, wrap),
+            vec!["aaa  ", "  bbb", "  ccc"]
+        );
+        let wrap = Wrap::new(WrapMode::Grapheme, 1);
+        assert_eq!(wrapped("abcdefg", 4, wrap), vec!["abcd", " efg"]);
+    }
+
+    fn clipped(text: &str, width: usize, align: Align, overflow: Overflow) -> String {
+        let bound = RenderBound::new(Some(width), None);
+        let lines = vec![text.to_owned()];
+        let block: TextBlock<u8> = TextBlock::clip_lines_with(id(), lines, bound, align, overflow);
+        row_text(&block, 0)
+    }
+
+    #[test]
+    fn alignment() {
+        assert_eq!(clipped("ab", 6, Align::Left, Overflow::Cut), "ab    ");
+        assert_eq!(clipped("ab", 6, Align::Center, Overflow::Cut), "  ab  ");
+        assert_eq!(clipped("ab", 5, Align::Center, Overflow::Cut), " ab  ");
+        assert_eq!(clipped("ab", 6, Align::Right, Overflow::Cut), "    ab");
+    }
+
+    #[test]
+    fn ellipsis() {
+        let path = "/usr/local/bin/tool";
+        assert_eq!(clipped(path, 8, Align::Left, Overflow::Cut), "/usr/loc");
+        assert_eq!(clipped(path, 8, Align::Left, Overflow::Ellipsis), "/usr/lo…");
+        assert_eq!(clipped(path, 8, Align::Left, Overflow::MiddleEllipsis), "/us…tool");
+        assert_eq!(clipped("short", 8, Align::Right, Overflow::Ellipsis), "   short");
+        assert_eq!(clipped("日本語", 4, Align::Left, Overflow::Ellipsis), "日 …");
+        assert_eq!(clipped(path, 1, Align::Left, Overflow::MiddleEllipsis), "…");
+        assert_eq!(clipped(path, 2, Align::Left, Overflow::MiddleEllipsis), "…l");
+        for &overflow in &[Overflow::Ellipsis, Overflow::MiddleEllipsis] {
+            assert_eq!(TextLine::<u8>::default().truncate(1, overflow).len, 0);
+            assert_eq!(TextLine::<u8>::default().truncate(0, overflow).len, 0);
+        }
+    }
+
+    #[test]
+    fn sanitized_text() {
+        assert_eq!(sanitize("a\tb", 0, 8), "a       b");
+        assert_eq!(sanitize("a\tb", 3, 4), "a    b");
+        assert_eq!(sanitize("日\tb", 0, 4), "日  b");
+        assert_eq!(sanitize("\x1b[31mred\r\n", 0, 8), "^[[31mred^M^J");
+        assert_eq!(sanitize("\x7f\u{9b}", 0, 8), "^?<U+009B>");
+        let seg: Segment<u8> = Segment::new_id(id(), "\tx".to_owned());
+        assert_eq!(seg.len, 9);
+    }
+
+    #[test]
+    fn rich_tabs_follow_columns() {
+        let line = RichLine::new().span("Key", "ab").span("Value", "\tc");
+        let tl: TextLine<u8> = line.into_text_line(id(), 4);
+        assert_eq!(tl.segments[1].text, "  c");
+        assert_eq!(tl.len, 5);
+
+        let line = RichLine::new().span("Key", "a\x01").span("Value", "\tc");
+        assert_eq!(line.width(4), 5);
+        assert_eq!(line.into_text_line::<u8>(id(), 4).len, 5);
+    }
+}
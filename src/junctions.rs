@@ -0,0 +1,273 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use ir::grapheme_width;
+use {ContentID, Name, TextLine};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Weight {
+    Light,
+    Heavy,
+    Double,
+}
+
+const UP: usize = 0;
+const DOWN: usize = 1;
+const LEFT: usize = 2;
+const RIGHT: usize = 3;
+
+type Arms = [Option<Weight>; 4];
+
+// Each glyph's arms, up, down, left and right: 'l' for light, 'h' heavy and 'd' double. The
+// rounded corners come after the square ones, so joins only ever produce square corners.
+const GLYPHS: &[(char, &str)] = &[
+    ('─', "  ll"),
+    ('━', "  hh"),
+    ('│', "ll  "),
+    ('┃', "hh  "),
+    ('┌', " l l"),
+    ('┍', " l h"),
+    ('┎', " h l"),
+    ('┏', " h h"),
+    ('┐', " ll "),
+    ('┑', " lh "),
+    ('┒', " hl "),
+    ('┓', " hh "),
+    ('└', "l  l"),
+    ('┕', "l  h"),
+    ('┖', "h  l"),
+    ('┗', "h  h"),
+    ('┘', "l l "),
+    ('┙', "l h "),
+    ('┚', "h l "),
+    ('┛', "h h "),
+    ('├', "ll l"),
+    ('┝', "ll h"),
+    ('┠', "hh l"),
+    ('┣', "hh h"),
+    ('┤', "lll "),
+    ('┥', "llh "),
+    ('┨', "hhl "),
+    ('┫', "hhh "),
+    ('┬', " lll"),
+    ('┯', " lhh"),
+    ('┰', " hll"),
+    ('┳', " hhh"),
+    ('┴', "l ll"),
+    ('┷', "l hh"),
+    ('┸', "h ll"),
+    ('┻', "h hh"),
+    ('┼', "llll"),
+    ('┿', "llhh"),
+    ('╂', "hhll"),
+    ('╋', "hhhh"),
+    ('═', "  dd"),
+    ('║', "dd  "),
+    ('╒', " l d"),
+    ('╓', " d l"),
+    ('╔', " d d"),
+    ('╕', " ld "),
+    ('╖', " dl "),
+    ('╗', " dd "),
+    ('╘', "l  d"),
+    ('╙', "d  l"),
+    ('╚', "d  d"),
+    ('╛', "l d "),
+    ('╜', "d l "),
+    ('╝', "d d "),
+    ('╞', "ll d"),
+    ('╟', "dd l"),
+    ('╠', "dd d"),
+    ('╡', "lld "),
+    ('╢', "ddl "),
+    ('╣', "ddd "),
+    ('╤', " ldd"),
+    ('╥', " dll"),
+    ('╦', " ddd"),
+    ('╧', "l dd"),
+    ('╨', "d ll"),
+    ('╩', "d dd"),
+    ('╪', "lldd"),
+    ('╫', "ddll"),
+    ('╬', "dddd"),
+    ('╭', " l l"),
+    ('╮', " ll "),
+    ('╯', "l l "),
+    ('╰', "l  l"),
+];
+
+fn parse_arms(spec: &str) -> Arms {
+    let mut arms = [None; 4];
+    for (arm, code) in arms.iter_mut().zip(spec.chars()) {
+        *arm = match code {
+            'l' => Some(Weight::Light),
+            'h' => Some(Weight::Heavy),
+            'd' => Some(Weight::Double),
+            _ => None,
+        };
+    }
+    arms
+}
+
+fn arms(grapheme: &str) -> Option<Arms> {
+    let mut chars = grapheme.chars();
+    let ch = chars.next()?;
+    if chars.next().is_some() {
+        return None;
+    }
+    GLYPHS
+        .iter()
+        .find(|(glyph, _)| *glyph == ch)
+        .map(|(_, spec)| parse_arms(spec))
+}
+
+// The first glyph listed with the given arms
+fn glyph(arms: Arms) -> Option<char> {
+    GLYPHS
+        .iter()
+        .find(|(_, spec)| parse_arms(spec) == arms)
+        .map(|(glyph, _)| *glyph)
+}
+
+// Only lines and borders join up, never box-drawing characters that happen to be in text. This
+// goes by widget type alone, so they still join when a class like "Dim" is given to both.
+fn is_rule<N: Name>(id: &ContentID<N>) -> bool {
+    matches!(id.widget_type, "Line" | "Border")
+}
+
+// Adds an arm toward every neighbour with an arm pointing back, keeping one weight per axis
+// so the result has a glyph. Returns None when nothing joins.
+fn join(own: Arms, neighbours: [Option<Arms>; 4]) -> Option<char> {
+    let mut arms = own;
+    for (dir, back) in [(UP, DOWN), (DOWN, UP), (LEFT, RIGHT), (RIGHT, LEFT)].iter() {
+        if arms[*dir].is_none() {
+            arms[*dir] = neighbours[*dir].and_then(|nb| nb[*back]);
+        }
+    }
+    if arms == own {
+        return None;
+    }
+    for (a, b) in [(UP, DOWN), (LEFT, RIGHT)].iter() {
+        if let Some(weight) = own[*a].or(own[*b]).or(arms[*a]).or(arms[*b]) {
+            arms[*a] = arms[*a].map(|_| weight);
+            arms[*b] = arms[*b].map(|_| weight);
+        }
+    }
+    glyph(arms)
+}
+
+// Replaces box-drawing cells of lines and borders where they meet with tees and crosses
+pub(crate) fn join_lines<N: Name>(lines: &mut [TextLine<N>]) {
+    let cells: Vec<Vec<Option<Arms>>> = lines
+        .iter()
+        .map(|line| {
+            let mut row = vec![];
+            for seg in &line.segments {
+                let rule = is_rule(&seg.id);
+                for grapheme in UnicodeSegmentation::graphemes(seg.text.as_str(), true) {
+                    row.push(arms(grapheme).filter(|_| rule));
+                    for _ in 1..grapheme_width(grapheme) {
+                        row.push(None);
+                    }
+                }
+            }
+            row
+        }).collect();
+    let cell = |row: Option<usize>, col: Option<usize>| -> Option<Arms> {
+        let (row, col) = (row?, col?);
+        cells.get(row).and_then(|r| r.get(col)).and_then(|c| *c)
+    };
+    for (row, line) in lines.iter_mut().enumerate() {
+        let mut col = 0;
+        for seg in &mut line.segments {
+            if !is_rule(&seg.id) {
+                col += seg.len;
+                continue;
+            }
+            let mut text = String::new();
+            let mut changed = false;
+            for grapheme in UnicodeSegmentation::graphemes(seg.text.as_str(), true) {
+                let neighbours = [
+                    cell(row.checked_sub(1), Some(col)),
+                    cell(Some(row + 1), Some(col)),
+                    cell(Some(row), col.checked_sub(1)),
+                    cell(Some(row), Some(col + 1)),
+                ];
+                match cell(Some(row), Some(col)).and_then(|own| join(own, neighbours)) {
+                    Some(joined) => {
+                        text.push(joined);
+                        changed = true;
+                    }
+                    None => text.push_str(grapheme),
+                }
+                col += grapheme_width(grapheme);
+            }
+            if changed {
+                seg.text = text;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{arms, glyph, join};
+    use test_util::row_text;
+    use widget::{Border, BorderStyle, Line, Linear, Log};
+    use {RenderBound, RenderContext, Widget};
+
+    #[test]
+    fn lines_meet() {
+        let (log1, log2) = (Log::new(None), Log::new(None));
+        let (log3, log4) = (Log::new(None), Log::new(None));
+        let (vline, hline) = (Line::vertical(), Line::horizontal());
+        let top = Linear::hbox(vec![&log1, &vline, &log2]);
+        let bottom = Linear::hbox(vec![&log3, &vline, &log4]);
+        let ui: Linear<u8> = Linear::vbox(vec![&top, &hline, &bottom]);
+        let bound = RenderBound::new(Some(5), Some(3));
+        let block = ui
+            .render(RenderContext::from_widget(bound, &ui))
+            .join_lines();
+        assert_eq!(row_text(&block, 0), "  │  ");
+        assert_eq!(row_text(&block, 1), "──┼──");
+        assert_eq!(row_text(&block, 2), "  │  ");
+
+        let ui: Linear<u8> = Linear::vbox(vec![&top, &hline]);
+        let block = ui
+            .render(RenderContext::from_widget(bound, &ui))
+            .join_lines();
+        assert_eq!(row_text(&block, 2), "──┴──");
+    }
+
+    #[test]
+    fn borders_meet_lines() {
+        let (log1, log2) = (Log::new(None), Log::new(None));
+        let hline = Line::horizontal();
+        let inner = Linear::vbox(vec![&log1, &hline, &log2]);
+        let ui: Border<u8> = Border::new(&inner).with_style(BorderStyle::Double);
+        let bound = RenderBound::new(Some(6), Some(5));
+        let block = ui
+            .render(RenderContext::from_widget(bound, &ui))
+            .join_lines();
+        assert_eq!(row_text(&block, 0), "╔════╗");
+        assert_eq!(row_text(&block, 2), "╟────╢");
+
+        // As when a modal dims everything under it
+        let block = ui
+            .render(RenderContext::from_widget(bound, &ui))
+            .with_class("Dim")
+            .join_lines();
+        assert_eq!(row_text(&block, 2), "╟────╢");
+    }
+
+    #[test]
+    fn weights() {
+        let up = |above: &str, ch: &str| join(arms(ch).unwrap(), [arms(above), None, None, None]);
+        assert_eq!(up("│", "━"), Some('┷'));
+        assert_eq!(up("┃", "─"), Some('┸'));
+        assert_eq!(up("│", "═"), Some('╧'));
+        assert_eq!(up("┃", "═"), None);
+        assert_eq!(up("─", "═"), None);
+        // Rounded corners only ever come out square
+        assert_eq!(glyph(arms("╭").unwrap()), Some('┌'));
+    }
+}
@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use input::parse_keys;
+use {KeyCode, KeyEvent, Modifiers, Name};
+
+// Actions the executor handles itself instead of passing to the app
+pub const FOCUS_NEXT: &str = "focus-next";
+pub const FOCUS_PREV: &str = "focus-prev";
+
+// What the executor should do with keys once the keymap has seen them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dispatch {
+    Action(&'static str),
+    Key(KeyEvent),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeymapError {
+    // The spec that failed to parse
+    InvalidKeys(String),
+    // A sequence with no keys, which could never be typed
+    Empty,
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeymapError::InvalidKeys(keys) => write!(f, "invalid key sequence {:?}", keys),
+            KeymapError::Empty => write!(f, "empty key sequence"),
+        }
+    }
+}
+
+// Binds key sequences to named actions. Bindings live in layers: the `None` layer is always
+// active, and a named layer only while that name has focus, taking precedence over `None`.
+pub struct Keymap<N: Name> {
+    layers: HashMap<Option<N>, Vec<(Vec<KeyEvent>, &'static str)>>,
+    timeout: Option<Duration>,
+    pending: Vec<KeyEvent>,
+    pending_focus: Option<N>,
+    last_key: Option<Instant>,
+}
+
+impl<N: Name> Default for Keymap<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: Name> Keymap<N> {
+    pub fn new() -> Self {
+        let layers = HashMap::new();
+        let timeout = Some(Duration::from_secs(1));
+        let pending = vec![];
+        let pending_focus = None;
+        let last_key = None;
+        Self {
+            layers,
+            timeout,
+            pending,
+            pending_focus,
+            last_key,
+        }
+    }
+    // With no timeout, a prefix waits for the next key forever, like Emacs
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    // Tab and Shift-Tab move focus through the widgets in render order. Bound globally, they
+    // take Tab from the focused widget, so completion in a Readline needs another key.
+    pub fn with_focus_keys(self) -> Self {
+        let back_tab = KeyEvent::new(KeyCode::Tab, Modifiers::SHIFT);
+        self.insert(None, vec![KeyCode::Tab.into()], FOCUS_NEXT)
+            .insert(None, vec![back_tab], FOCUS_PREV)
+    }
+    // `keys` uses KeyEvent's notation, like "C-x C-s" or "g g"
+    pub fn bind(
+        self,
+        layer: Option<N>,
+        keys: &str,
+        action: &'static str,
+    ) -> Result<Self, KeymapError> {
+        let parsed = parse_keys(keys).map_err(|_| KeymapError::InvalidKeys(keys.to_owned()))?;
+        self.bind_keys(layer, parsed, action)
+    }
+    pub fn bind_keys(
+        self,
+        layer: Option<N>,
+        keys: Vec<KeyEvent>,
+        action: &'static str,
+    ) -> Result<Self, KeymapError> {
+        if keys.is_empty() {
+            return Err(KeymapError::Empty);
+        }
+        Ok(self.insert(layer, keys, action))
+    }
+    fn insert(mut self, layer: Option<N>, keys: Vec<KeyEvent>, action: &'static str) -> Self {
+        let bindings = self.layers.entry(layer).or_default();
+        bindings.retain(|(bound, _)| *bound != keys);
+        bindings.push((keys, action));
+        self
+    }
+    // The keys of an unfinished sequence, for a pending keys indicator
+    pub fn pending(&self) -> &[KeyEvent] {
+        &self.pending
+    }
+    pub fn deadline(&self) -> Option<Instant> {
+        match (self.pending.is_empty(), self.timeout, self.last_key) {
+            (false, Some(timeout), Some(last_key)) => Some(last_key + timeout),
+            _ => None,
+        }
+    }
+    pub fn handle_key(&mut self, focus: Option<N>, key: KeyEvent, now: Instant) -> Vec<Dispatch> {
+        let mut out = self.handle_timeout(now);
+        if self.pending_focus != focus {
+            out.extend(self.flush());
+        }
+        self.pending_focus = focus;
+        self.last_key = Some(now);
+        self.pending.push(key);
+        if self.has_longer(focus) {
+            return out;
+        }
+        if let Some(action) = self.lookup(focus) {
+            self.pending.clear();
+            out.push(Dispatch::Action(action));
+            return out;
+        }
+        // The sequence went nowhere, so resolve the earlier keys and start over from this one
+        self.pending.pop();
+        out.extend(self.flush());
+        self.pending.push(key);
+        if self.has_longer(focus) {
+            return out;
+        }
+        self.pending.clear();
+        match self.lookup_keys(focus, &[key]) {
+            Some(action) => out.push(Dispatch::Action(action)),
+            None => out.push(Dispatch::Key(key)),
+        }
+        out
+    }
+    // Resolves a sequence that has waited past the deadline, preferring a binding that it
+    // completes on its own over the longer ones it was a prefix of
+    pub fn handle_timeout(&mut self, now: Instant) -> Vec<Dispatch> {
+        match self.deadline() {
+            Some(deadline) if deadline <= now => self.flush(),
+            _ => vec![],
+        }
+    }
+    fn flush(&mut self) -> Vec<Dispatch> {
+        let focus = self.pending_focus;
+        let out = match self.lookup(focus) {
+            Some(action) => vec![Dispatch::Action(action)],
+            None => self.pending.iter().cloned().map(Dispatch::Key).collect(),
+        };
+        self.pending.clear();
+        out
+    }
+    fn bindings(&self, focus: Option<N>) -> impl Iterator<Item = &(Vec<KeyEvent>, &'static str)> {
+        let focused = focus.and_then(|name| self.layers.get(&Some(name)));
+        let global = self.layers.get(&None);
+        focused.into_iter().chain(global).flatten()
+    }
+    fn lookup(&self, focus: Option<N>) -> Option<&'static str> {
+        self.lookup_keys(focus, &self.pending)
+    }
+    fn lookup_keys(&self, focus: Option<N>, keys: &[KeyEvent]) -> Option<&'static str> {
+        self.bindings(focus)
+            .find(|(bound, _)| &bound[..] == keys)
+            .map(|(_, action)| *action)
+    }
+    fn has_longer(&self, focus: Option<N>) -> bool {
+        let pending = &self.pending;
+        self.bindings(focus)
+            .any(|(bound, _)| bound.len() > pending.len() && bound.starts_with(pending))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Dispatch, Keymap, KeymapError};
+    use std::time::{Duration, Instant};
+    use {KeyCode, KeyEvent};
+
+    fn key(ch: char) -> KeyEvent {
+        KeyCode::Char(ch).into()
+    }
+
+    #[test]
+    fn chords() {
+        let mut keymap: Keymap<u8> = Keymap::new()
+            .with_timeout(None)
+            .bind(None, "C-x C-s", "save")
+            .and_then(|keymap| keymap.bind(None, "C-x C-c", "quit"))
+            .unwrap();
+        let now = Instant::now();
+        assert!(keymap.handle_key(None, KeyEvent::ctrl('x'), now).is_empty());
+        assert_eq!(keymap.pending(), &[KeyEvent::ctrl('x')]);
+        assert_eq!(
+            keymap.handle_key(None, KeyEvent::ctrl('s'), now),
+            vec![Dispatch::Action("save")]
+        );
+        assert!(keymap.pending().is_empty());
+        keymap.handle_key(None, KeyEvent::ctrl('x'), now);
+        assert_eq!(
+            keymap.handle_key(None, key('a'), now),
+            vec![Dispatch::Key(KeyEvent::ctrl('x')), Dispatch::Key(key('a'))]
+        );
+    }
+
+    #[test]
+    fn invalid_bindings() {
+        let bogus = Keymap::<u8>::new().bind(None, "C-x Bogus", "save");
+        assert_eq!(
+            bogus.err(),
+            Some(KeymapError::InvalidKeys("C-x Bogus".to_owned()))
+        );
+        let empty = Keymap::<u8>::new().bind(None, " ", "nothing");
+        assert_eq!(empty.err(), Some(KeymapError::Empty));
+        let empty = Keymap::<u8>::new().bind_keys(None, vec![], "nothing");
+        assert_eq!(empty.err(), Some(KeymapError::Empty));
+    }
+
+    #[test]
+    fn focus_layers() {
+        let mut keymap = Keymap::new()
+            .bind(None, "q", "quit")
+            .and_then(|keymap| keymap.bind(Some(1), "q", "close"))
+            .and_then(|keymap| keymap.bind(Some(1), "g g", "top"))
+            .unwrap();
+        let now = Instant::now();
+        assert_eq!(
+            keymap.handle_key(Some(2), key('q'), now),
+            vec![Dispatch::Action("quit")]
+        );
+        assert_eq!(
+            keymap.handle_key(Some(1), key('q'), now),
+            vec![Dispatch::Action("close")]
+        );
+        assert!(keymap.handle_key(Some(1), key('g'), now).is_empty());
+        assert_eq!(
+            keymap.handle_key(Some(1), key('g'), now),
+            vec![Dispatch::Action("top")]
+        );
+        assert_eq!(
+            keymap.handle_key(Some(2), key('g'), now),
+            vec![Dispatch::Key(key('g'))]
+        );
+    }
+
+    #[test]
+    fn timeouts() {
+        let mut keymap: Keymap<u8> = Keymap::new()
+            .with_timeout(Some(Duration::from_millis(500)))
+            .bind(None, "g", "go")
+            .and_then(|keymap| keymap.bind(None, "g g", "top"))
+            .unwrap();
+        let start = Instant::now();
+        assert!(keymap.handle_key(None, key('g'), start).is_empty());
+        assert_eq!(keymap.deadline(), Some(start + Duration::from_millis(500)));
+        assert!(keymap.handle_timeout(start).is_empty());
+        let late = start + Duration::from_secs(1);
+        assert_eq!(keymap.handle_timeout(late), vec![Dispatch::Action("go")]);
+        assert_eq!(keymap.deadline(), None);
+        keymap.handle_key(None, key('g'), late);
+        assert_eq!(
+            keymap.handle_key(None, key('x'), late + Duration::from_secs(1)),
+            vec![Dispatch::Action("go"), Dispatch::Key(key('x'))]
+        );
+    }
+}
@@ -10,11 +10,15 @@
 #![cfg_attr(feature = "cargo-clippy", allow(if_same_then_else))]
 #![cfg_attr(feature = "cargo-clippy", allow(new_without_default_derive))]
 
+#[macro_use]
+extern crate bitflags;
 extern crate itertools;
 extern crate libc;
 extern crate signal_hook;
 extern crate termion;
+extern crate unicode_linebreak;
 extern crate unicode_segmentation;
+extern crate unicode_width;
 #[macro_use]
 extern crate log;
 
@@ -26,21 +30,37 @@ pub mod executor;
 mod indextree;
 pub mod input;
 pub mod ir;
+mod junctions;
+pub mod keymap;
+pub mod theme;
+#[cfg(test)]
+mod test_util;
 pub mod widget;
 
 pub use backend::TermionBackend;
 pub use executor::Executor;
-pub use input::{InputEvent, Key, MouseEvent};
-pub use ir::{ContentID, Frame, FrameLine, Segment, TextBlock, TextLine};
+pub use input::{
+    FocusEvent, HoverEvent, InputEvent, KeyCode, KeyEvent, KeyParseError, Modifiers, MouseAction,
+    MouseButton, MouseEvent, ScrollDirection,
+};
+pub use ir::{
+    Align, ContentID, Frame, FrameLine, Overflow, RichLine, Segment, TextBlock, TextLine, Wrap,
+    WrapMode,
+};
+pub use keymap::{Dispatch, Keymap, KeymapError};
+pub use theme::{Theme, ThemeError};
 pub use widget::{
-    App, BackendContext, EventContext, KeyCallback, Line, Linear, MouseCallback, Name, Readline,
-    ReadlineEvent, RenderBackend, RenderContext, ShouldPropagate, Widget,
+    App, BackendContext, EventContext, FocusCallback, HoverCallback, KeyCallback, Line, Linear,
+    MouseCallback, Name, Readline, ReadlineEvent, RenderBackend, RenderContext, ShouldPropagate,
+    Widget,
 };
 
 #[derive(Debug, PartialEq)]
 pub enum AppEvent<N: Name> {
     Exit,
     SetFocus(N),
+    FocusNext,
+    FocusPrev,
     Redraw,
 }
 
@@ -88,36 +108,73 @@ impl Direction {
     }
 }
 
+// How much room a widget wants along one axis. Layouts never go below `min` if they can help
+// it, hand out room up to `preferred` next, and then share what's left up to `max`.
 #[derive(Debug, PartialEq, Clone, Copy)]
-pub enum GrowthPolicy {
-    FixedSize,
-    Greedy,
+pub struct SizeRange {
+    pub min: usize,
+    pub preferred: usize,
+    pub max: Option<usize>,
+}
+
+impl SizeRange {
+    pub fn new(min: usize, preferred: usize, max: Option<usize>) -> Self {
+        let preferred = preferred.max(min);
+        let max = max.map(|max| max.max(preferred));
+        Self {
+            min,
+            preferred,
+            max,
+        }
+    }
+    pub fn fixed(size: usize) -> Self {
+        Self::new(size, size, Some(size))
+    }
+    pub fn at_least(min: usize) -> Self {
+        Self::new(min, min, None)
+    }
+    pub fn clamp(&self, size: usize) -> usize {
+        let size = size.max(self.min);
+        self.max.map_or(size, |max| size.min(max))
+    }
 }
 
-impl Default for GrowthPolicy {
+impl Default for SizeRange {
     fn default() -> Self {
-        GrowthPolicy::Greedy
+        Self::at_least(0)
     }
 }
 
-#[derive(Default, Debug, PartialEq)]
-pub struct FullGrowthPolicy {
-    pub width: GrowthPolicy,
-    pub height: GrowthPolicy,
+// What a widget reports for a RenderBound, so layouts can size it before rendering
+#[derive(Default, Debug, PartialEq, Clone, Copy)]
+pub struct SizeHint {
+    pub width: SizeRange,
+    pub height: SizeRange,
 }
 
-impl FullGrowthPolicy {
-    pub fn fixed_height() -> FullGrowthPolicy {
-        let width = GrowthPolicy::Greedy;
-        let height = GrowthPolicy::FixedSize;
-        FullGrowthPolicy { width, height }
+impl SizeHint {
+    pub fn new(width: SizeRange, height: SizeRange) -> Self {
+        Self { width, height }
+    }
+    pub fn fixed_height(height: usize) -> Self {
+        let width = SizeRange::default();
+        let height = SizeRange::fixed(height);
+        Self { width, height }
     }
-    pub fn fixed_width() -> FullGrowthPolicy {
-        let width = GrowthPolicy::FixedSize;
-        let height = GrowthPolicy::Greedy;
-        FullGrowthPolicy { width, height }
+    pub fn fixed_width(width: usize) -> Self {
+        let width = SizeRange::fixed(width);
+        let height = SizeRange::default();
+        Self { width, height }
+    }
+    pub fn min_height(mut self, height: usize) -> Self {
+        self.height = SizeRange::new(height, self.height.preferred, self.height.max);
+        self
     }
-    pub fn in_direction(&self, dir: Direction) -> GrowthPolicy {
+    pub fn min_width(mut self, width: usize) -> Self {
+        self.width = SizeRange::new(width, self.width.preferred, self.width.max);
+        self
+    }
+    pub fn in_direction(&self, dir: Direction) -> SizeRange {
         match dir {
             Direction::Vertical => self.height,
             Direction::Horizontal => self.width,
@@ -125,7 +182,7 @@ impl FullGrowthPolicy {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Pos {
     pub col: usize,
     pub row: usize,
@@ -164,6 +221,30 @@ impl ops::SubAssign for Pos {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub pos: Pos,
+    pub size: Size,
+}
+
+impl Rect {
+    pub fn new(pos: Pos, size: Size) -> Self {
+        Self { pos, size }
+    }
+    pub fn contains(&self, pos: Pos) -> bool {
+        pos.col >= self.pos.col
+            && pos.row >= self.pos.row
+            && pos.col < self.pos.col + self.size.cols
+            && pos.row < self.pos.row + self.size.rows
+    }
+    pub fn covers(&self, other: Rect) -> bool {
+        other.pos.col >= self.pos.col
+            && other.pos.row >= self.pos.row
+            && other.pos.col + other.size.cols <= self.pos.col + self.size.cols
+            && other.pos.row + other.size.rows <= self.pos.row + self.size.rows
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct RenderBound {
     pub width: Option<usize>, // Maybe this should be NonZeroUsize?
@@ -230,7 +311,7 @@ impl From<Size> for RenderBound {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Color {
     LightBlack,
     LightBlue,
@@ -251,3 +332,88 @@ pub enum Color {
     Rgb(u8, u8, u8),
     Reset,
 }
+
+bitflags! {
+    #[derive(Default)]
+    pub struct Attributes: u8 {
+        const BOLD = 0b0000_0001;
+        const DIM = 0b0000_0010;
+        const ITALIC = 0b0000_0100;
+        const UNDERLINE = 0b0000_1000;
+        const REVERSE = 0b0001_0000;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub attrs: Attributes,
+}
+
+impl Style {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn fg(mut self, color: Color) -> Self {
+        self.fg = Some(color);
+        self
+    }
+    pub fn bg(mut self, color: Color) -> Self {
+        self.bg = Some(color);
+        self
+    }
+    pub fn attrs(mut self, attrs: Attributes) -> Self {
+        self.attrs |= attrs;
+        self
+    }
+    pub fn bold(self) -> Self {
+        self.attrs(Attributes::BOLD)
+    }
+    pub fn dim(self) -> Self {
+        self.attrs(Attributes::DIM)
+    }
+    pub fn italic(self) -> Self {
+        self.attrs(Attributes::ITALIC)
+    }
+    pub fn underline(self) -> Self {
+        self.attrs(Attributes::UNDERLINE)
+    }
+    pub fn reverse(self) -> Self {
+        self.attrs(Attributes::REVERSE)
+    }
+}
+
+impl From<(Option<Color>, Option<Color>)> for Style {
+    fn from((fg, bg): (Option<Color>, Option<Color>)) -> Self {
+        let attrs = Attributes::empty();
+        Style { fg, bg, attrs }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CursorShape {
+    Block,
+    Underline,
+    Bar,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CursorStyle {
+    // Whatever the user's terminal is configured to show
+    #[default]
+    Default,
+    Hidden,
+    Shaped { shape: CursorShape, blink: bool },
+}
+
+impl CursorStyle {
+    pub fn steady(shape: CursorShape) -> Self {
+        let blink = false;
+        CursorStyle::Shaped { shape, blink }
+    }
+    pub fn blinking(shape: CursorShape) -> Self {
+        let blink = true;
+        CursorStyle::Shaped { shape, blink }
+    }
+}
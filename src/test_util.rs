@@ -0,0 +1,26 @@
+// Helpers shared by the test modules
+use std::sync::mpsc::{channel, Receiver};
+
+use executor::Event;
+use {EventContext, Modifiers, MouseAction, MouseButton, MouseEvent, Name, Pos, TextBlock};
+
+// The text of a row, across all its segments
+pub fn row_text<N: Name>(block: &TextBlock<N>, row: usize) -> String {
+    block.lines[row]
+        .segments
+        .iter()
+        .map(|s| s.text.as_str())
+        .collect()
+}
+
+// A left button press at a position in the block
+pub fn click(col: usize, row: usize) -> MouseEvent {
+    let action = MouseAction::Press(MouseButton::Left);
+    MouseEvent::new(action, Pos::new(col, row), Modifiers::empty())
+}
+
+// A context for handlers, and the receiver to see what they sent
+pub fn event_context<N: Name>() -> (EventContext<N>, Receiver<Event<N>>) {
+    let (sender, receiver) = channel();
+    (EventContext::new(sender), receiver)
+}
@@ -0,0 +1,362 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use {Attributes, Color, ContentID, Name, Style};
+
+// A theme file has one rule per line, matching (name, widget_type, class) and then listing
+// styles after a colon:
+//
+//   # name     widget     class     [focused] : styles
+//   *          Log        *                   : fg=green
+//   Log1       *          *                   : fg=light-red bold
+//   Number*    *          *                   : fg=#ffaa00
+//   *          Readline   *         focused   : bg=blue
+//
+// Names are matched against their Debug form. `*` matches anything, including no name, and a
+// trailing `*` matches by prefix. Every matching rule applies, the more specific ones later:
+// a name counts for more than a widget type, which counts for more than a class, and focused
+// rules and rules further down the file break ties.
+//
+// Selectors are split on whitespace and end at the first colon, with no quoting, so a name whose
+// Debug form has a space or a `:` in it, like `Tab { id: 1 }`, can only be matched by a prefix
+// that stops before them, like `Tab*`.
+pub struct Theme {
+    path: Option<PathBuf>,
+    state: RwLock<ThemeState>,
+}
+
+struct ThemeState {
+    // Modification time and length, since some filesystems only keep whole seconds
+    stamp: Option<(SystemTime, u64)>,
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug)]
+pub enum ThemeError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ThemeError::Io(err) => write!(f, "{}", err),
+            ThemeError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl From<io::Error> for ThemeError {
+    fn from(err: io::Error) -> Self {
+        ThemeError::Io(err)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Pattern {
+    Any,
+    Prefix(String),
+    Exact(String),
+}
+
+impl Pattern {
+    fn parse(text: &str) -> Self {
+        if text == "*" {
+            Pattern::Any
+        } else if let Some(prefix) = text.strip_suffix('*') {
+            Pattern::Prefix(prefix.to_owned())
+        } else {
+            Pattern::Exact(text.to_owned())
+        }
+    }
+    fn matches(&self, value: Option<&str>) -> bool {
+        match (self, value) {
+            (Pattern::Any, _) => true,
+            (Pattern::Prefix(prefix), Some(value)) => value.starts_with(prefix.as_str()),
+            (Pattern::Exact(exact), Some(value)) => exact == value,
+            (_, None) => false,
+        }
+    }
+    fn is_any(&self) -> bool {
+        *self == Pattern::Any
+    }
+}
+
+struct Rule {
+    name: Pattern,
+    widget_type: Pattern,
+    class: Pattern,
+    focused: bool,
+    style: Style,
+}
+
+impl Rule {
+    fn precedence(&self) -> (u8, bool) {
+        let specificity = (!self.name.is_any() as u8) * 4
+            + (!self.widget_type.is_any() as u8) * 2
+            + (!self.class.is_any() as u8);
+        (specificity, self.focused)
+    }
+}
+
+impl Theme {
+    pub fn parse(text: &str) -> Result<Self, ThemeError> {
+        let path = None;
+        let stamp = None;
+        let rules = parse_rules(text)?;
+        let state = RwLock::new(ThemeState { stamp, rules });
+        Ok(Self { path, state })
+    }
+    // Themes loaded from a file are reloaded by the Executor when the file changes
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ThemeError> {
+        let path = path.as_ref().to_owned();
+        let stamp = file_stamp(&path)?;
+        let rules = parse_rules(&fs::read_to_string(&path)?)?;
+        let state = RwLock::new(ThemeState { stamp, rules });
+        Ok(Self {
+            path: Some(path),
+            state,
+        })
+    }
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+    // Returns whether the rules changed. On error, the old rules stay in place.
+    pub fn reload_if_changed(&self) -> Result<bool, ThemeError> {
+        let path = match self.path {
+            Some(ref path) => path,
+            None => return Ok(false),
+        };
+        let stamp = file_stamp(path)?;
+        if stamp == self.state.read().unwrap().stamp {
+            return Ok(false);
+        }
+        let rules = parse_rules(&fs::read_to_string(path)?)?;
+        let mut state = self.state.write().unwrap();
+        state.stamp = stamp;
+        state.rules = rules;
+        Ok(true)
+    }
+    pub fn style<N: Name>(&self, cid: ContentID<N>) -> Style {
+        let name = cid.name.map(|n| format!("{:?}", n));
+        let state = self.state.read().unwrap();
+        let matching = state.rules.iter().filter(|rule| {
+            rule.name.matches(name.as_deref())
+                && rule.widget_type.matches(Some(cid.widget_type))
+                && rule.class.matches(Some(cid.class))
+                && (cid.focused || !rule.focused)
+        });
+        let mut style = Style::new();
+        for rule in matching {
+            if let Some(fg) = rule.style.fg {
+                style.fg = Some(fg);
+            }
+            if let Some(bg) = rule.style.bg {
+                style.bg = Some(bg);
+            }
+            style.attrs |= rule.style.attrs;
+        }
+        style
+    }
+}
+
+impl fmt::Debug for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Theme")
+            .field("path", &self.path)
+            .field("rules", &self.state.read().unwrap().rules.len())
+            .finish()
+    }
+}
+
+// Used by the Executor's watcher to poll for changes without reading the whole file
+pub(crate) fn file_stamp(path: &Path) -> io::Result<Option<(SystemTime, u64)>> {
+    let metadata = fs::metadata(path)?;
+    Ok(metadata.modified().ok().map(|time| (time, metadata.len())))
+}
+
+fn parse_rules(text: &str) -> Result<Vec<Rule>, ThemeError> {
+    let mut rules = vec![];
+    for (idx, line) in text.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |message: String| ThemeError::Parse {
+            line: idx + 1,
+            message,
+        };
+        let colon = line
+            .find(':')
+            .ok_or_else(|| error("expected ':' between selector and styles".to_owned()))?;
+        let selector: Vec<&str> = line[..colon].split_whitespace().collect();
+        let focused = match selector.get(3) {
+            None => false,
+            Some(&"focused") if selector.len() == 4 => true,
+            _ => return Err(error(format!("bad selector {:?}", &line[..colon]))),
+        };
+        if selector.len() < 3 {
+            return Err(error(format!("bad selector {:?}", &line[..colon])));
+        }
+        let mut style = Style::new();
+        for word in line[colon + 1..].split_whitespace() {
+            style = parse_style_word(style, word)
+                .ok_or_else(|| error(format!("bad style {:?}", word)))?;
+        }
+        rules.push(Rule {
+            name: Pattern::parse(selector[0]),
+            widget_type: Pattern::parse(selector[1]),
+            class: Pattern::parse(selector[2]),
+            focused,
+            style,
+        });
+    }
+    // A stable sort keeps file order among rules of equal precedence
+    rules.sort_by_key(|rule| rule.precedence());
+    Ok(rules)
+}
+
+// A comment starts at a '#' that begins a word, so colors like fg=#ffaa00 are left alone
+fn strip_comment(line: &str) -> &str {
+    let mut prev = ' ';
+    for (idx, ch) in line.char_indices() {
+        if ch == '#' && prev.is_whitespace() {
+            return &line[..idx];
+        }
+        prev = ch;
+    }
+    line
+}
+
+fn parse_style_word(style: Style, word: &str) -> Option<Style> {
+    let style = match word {
+        "bold" => style.attrs(Attributes::BOLD),
+        "dim" => style.attrs(Attributes::DIM),
+        "italic" => style.attrs(Attributes::ITALIC),
+        "underline" => style.attrs(Attributes::UNDERLINE),
+        "reverse" => style.attrs(Attributes::REVERSE),
+        _ if word.starts_with("fg=") => style.fg(parse_color(&word[3..])?),
+        _ if word.starts_with("bg=") => style.bg(parse_color(&word[3..])?),
+        _ => return None,
+    };
+    Some(style)
+}
+
+fn parse_color(text: &str) -> Option<Color> {
+    let color = match text.to_lowercase().replace('_', "-").as_str() {
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "white" => Color::White,
+        "light-black" => Color::LightBlack,
+        "light-red" => Color::LightRed,
+        "light-green" => Color::LightGreen,
+        "light-yellow" => Color::LightYellow,
+        "light-blue" => Color::LightBlue,
+        "light-magenta" => Color::LightMagenta,
+        "light-cyan" => Color::LightCyan,
+        "light-white" => Color::LightWhite,
+        "default" | "reset" => Color::Reset,
+        hex if hex.starts_with('#') && hex.len() == 7 => {
+            let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+            Color::Rgb(channel(1)?, channel(3)?, channel(5)?)
+        }
+        _ => return None,
+    };
+    Some(color)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempdir;
+    use super::{Theme, ThemeError};
+    use std::fs;
+    use {Color, ContentID, Style};
+
+    const THEME: &str = "
+        # name    widget     class    [focused] : styles
+        Log1      *          *                  : fg=light-red bold
+        *         Log        *                  : fg=green bg=black
+        Number*   *          *                  : fg=#ffaa00 # orange
+        *         Readline   *        focused   : bg=blue
+        *         *          *                  : fg=white
+    ";
+
+    #[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+    enum Names {
+        Log1,
+        Log2,
+        Input,
+        Number(usize),
+    }
+
+    fn id(name: Option<Names>, widget: &'static str, class: &'static str) -> ContentID<Names> {
+        ContentID::new(name, widget, class)
+    }
+
+    #[test]
+    fn precedence() {
+        let theme = Theme::parse(THEME).unwrap();
+        let log1 = theme.style(id(Some(Names::Log1), "Log", "Content"));
+        assert_eq!(
+            log1,
+            Style::new().fg(Color::LightRed).bg(Color::Black).bold()
+        );
+        let log2 = theme.style(id(Some(Names::Log2), "Log", "Content"));
+        assert_eq!(log2, Style::new().fg(Color::Green).bg(Color::Black));
+        let number = theme.style(id(Some(Names::Number(7)), "Log", "Content"));
+        assert_eq!(number.fg, Some(Color::Rgb(0xff, 0xaa, 0x00)));
+        assert_eq!(
+            theme.style(id(None, "Line", "Line")),
+            Style::new().fg(Color::White)
+        );
+    }
+
+    #[test]
+    fn focused_rules() {
+        let theme = Theme::parse(THEME).unwrap();
+        let mut input = id(Some(Names::Input), "Readline", "Buffer");
+        assert_eq!(theme.style(input).bg, None);
+        input.focused = true;
+        assert_eq!(theme.style(input).bg, Some(Color::Blue));
+    }
+
+    #[test]
+    fn parse_errors() {
+        match Theme::parse("* * * : fg=red\n* * : bold") {
+            Err(ThemeError::Parse { line, .. }) => assert_eq!(line, 2),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(Theme::parse("* * * : fg=mauve").is_err());
+    }
+
+    #[test]
+    fn reload() {
+        let td = tempdir::TempDir::new("theme").unwrap();
+        let path = td.path().join("test.theme");
+        fs::write(&path, "* * * : fg=red").unwrap();
+        let theme = Theme::load(&path).unwrap();
+        assert!(!theme.reload_if_changed().unwrap());
+        fs::write(&path, "* * * : fg=blue bold").unwrap();
+        assert!(theme.reload_if_changed().unwrap());
+        assert_eq!(
+            theme.style(id(None, "Log", "Content")).fg,
+            Some(Color::Blue)
+        );
+        fs::write(&path, "* * * : fg=mauve").unwrap();
+        assert!(theme.reload_if_changed().is_err());
+        assert_eq!(
+            theme.style(id(None, "Log", "Content")).fg,
+            Some(Color::Blue)
+        );
+        td.close().unwrap();
+    }
+}
@@ -1,26 +1,42 @@
-use input::{InputEvent, Key, MouseEvent};
+use input::{FocusEvent, HoverEvent, InputEvent, KeyEvent, MouseEvent};
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::sync::mpsc::Sender;
 
 use AppEvent;
 
+pub mod align;
+pub mod border;
+pub mod grid;
 pub mod layout;
 pub mod line;
 pub mod list;
 pub mod log;
+pub mod padding;
 pub mod readline;
 pub mod simple_input;
+pub mod stack;
+pub mod tabs;
 
-pub use self::layout::Linear;
+pub use self::align::VAlign;
+pub use self::border::{Border, BorderStyle};
+pub use self::grid::Grid;
+pub use self::layout::{Constraint, Length, Linear};
 pub use self::line::Line;
 pub use self::list::List;
 pub use self::log::Log;
+pub use self::padding::Padding;
 pub use self::readline::{Readline, ReadlineEvent};
 pub use self::simple_input::SimpleInput;
+pub use self::stack::{Anchor, Stack};
+pub use self::tabs::{Tabs, TabsEvent, TabsListener, TabsState};
 
 use executor::Event;
-use {Color, ContentID, Frame, FullGrowthPolicy, Pos, RenderBound, Shared, Size, TextBlock};
+use ir::DEFAULT_TAB_WIDTH;
+use {
+    Align, ContentID, Frame, Overflow, RenderBound, RichLine, Segment, Shared, Size, SizeHint,
+    Style, TextBlock, TextLine, Theme, Wrap,
+};
 
 pub trait Name: Hash + Eq + Clone + Copy + Debug + Send {}
 
@@ -31,15 +47,20 @@ pub enum ShouldPropagate {
     Stop,
 }
 
-pub type KeyCallback<N> = Box<Fn(&EventContext<N>, Key) -> ShouldPropagate>;
-// XXX TODO need to use internal mouse event type instead of termion's
-pub type MouseCallback<N> = Box<Fn(&EventContext<N>, Pos, MouseEvent) -> ShouldPropagate>;
+pub type KeyCallback<N> = Box<Fn(&EventContext<N>, KeyEvent) -> ShouldPropagate>;
+pub type MouseCallback<N> = Box<Fn(&EventContext<N>, MouseEvent) -> ShouldPropagate>;
+pub type HoverCallback<N> = Box<Fn(&EventContext<N>, HoverEvent) -> ShouldPropagate>;
+pub type FocusCallback<N> = Box<Fn(&EventContext<N>, FocusEvent)>;
 
 #[derive(Clone)]
 pub struct RenderContext<N: Name> {
     bound: RenderBound,
     name: Option<N>,
     widget_type: &'static str,
+    wrap: Wrap,
+    align: Align,
+    overflow: Overflow,
+    tab_width: usize,
 }
 
 impl<N: Name> RenderContext<N> {
@@ -49,14 +70,44 @@ impl<N: Name> RenderContext<N> {
         Self::new(bound, name, widget_type)
     }
     fn new(bound: RenderBound, name: Option<N>, widget_type: &'static str) -> Self {
+        let wrap = Wrap::default();
+        let align = Align::default();
+        let overflow = Overflow::default();
+        let tab_width = DEFAULT_TAB_WIDTH;
         Self {
             bound,
             name,
             widget_type,
+            wrap,
+            align,
+            overflow,
+            tab_width,
         }
     }
     pub fn with_bound(&self, bound: RenderBound) -> Self {
-        Self::new(bound, self.name, self.widget_type)
+        let mut ctx = self.clone();
+        ctx.bound = bound;
+        ctx
+    }
+    pub fn with_wrap(&self, wrap: Wrap) -> Self {
+        let mut ctx = self.clone();
+        ctx.wrap = wrap;
+        ctx
+    }
+    pub fn with_align(&self, align: Align) -> Self {
+        let mut ctx = self.clone();
+        ctx.align = align;
+        ctx
+    }
+    pub fn with_overflow(&self, overflow: Overflow) -> Self {
+        let mut ctx = self.clone();
+        ctx.overflow = overflow;
+        ctx
+    }
+    pub fn with_tab_width(&self, tab_width: usize) -> Self {
+        let mut ctx = self.clone();
+        ctx.tab_width = tab_width;
+        ctx
     }
     pub fn render_sized(&self, bound: RenderBound, widget: &dyn Widget<N>) -> TextBlock<N> {
         let block = widget.render(Self::from_widget(bound, widget));
@@ -84,13 +135,44 @@ impl<N: Name> RenderContext<N> {
     pub fn bound(&self) -> RenderBound {
         self.bound
     }
+    pub fn tab_width(&self) -> usize {
+        self.tab_width
+    }
+    pub fn content_id(&self, class: &'static str) -> ContentID<N> {
+        ContentID::new(self.name, self.widget_type, class)
+    }
+    fn text_lines(&self, id: ContentID<N>, lines: Vec<String>) -> Vec<TextLine<N>> {
+        lines
+            .into_iter()
+            .map(|l| Segment::new_at(id, l, 0, self.tab_width).into())
+            .collect()
+    }
     pub fn clip_lines(&self, class: &'static str, lines: Vec<String>) -> TextBlock<N> {
-        let id = ContentID::new(self.name, class, self.widget_type);
-        TextBlock::clip_lines(id, lines, self.bound)
+        let id = self.content_id(class);
+        let lines = self.text_lines(id, lines);
+        TextBlock::clip_text_lines_with(id, lines, self.bound, self.align, self.overflow)
     }
     pub fn wrap_lines(&self, class: &'static str, lines: Vec<String>) -> TextBlock<N> {
-        let id = ContentID::new(self.name, class, self.widget_type);
-        TextBlock::wrap_lines(id, lines, self.bound)
+        let id = self.content_id(class);
+        let lines = self.text_lines(id, lines);
+        TextBlock::wrap_text_lines_with(id, lines, self.bound, self.wrap, self.align)
+    }
+    // `class` styles any padding around the spans
+    pub fn clip_rich(&self, class: &'static str, lines: Vec<RichLine>) -> TextBlock<N> {
+        let id = self.content_id(class);
+        let lines = lines
+            .into_iter()
+            .map(|l| l.into_text_line(id, self.tab_width))
+            .collect();
+        TextBlock::clip_text_lines_with(id, lines, self.bound, self.align, self.overflow)
+    }
+    pub fn wrap_rich(&self, class: &'static str, lines: Vec<RichLine>) -> TextBlock<N> {
+        let id = self.content_id(class);
+        let lines = lines
+            .into_iter()
+            .map(|l| l.into_text_line(id, self.tab_width))
+            .collect();
+        TextBlock::wrap_text_lines_with(id, lines, self.bound, self.wrap, self.align)
     }
     /*    fn line<F: Into<Fragment>>(&self, content: F) -> Block<N> {
         let fragment: Fragment = content.into();
@@ -161,8 +243,10 @@ where
 
     fn widget_type(&self) -> &'static str;
 
-    fn growth_policy(&self) -> FullGrowthPolicy {
-        FullGrowthPolicy::default()
+    // `bound` holds whatever the layout already knows, like the width of a vbox. The widget
+    // will then be rendered at a size within the bound, ideally within the hint.
+    fn size_hint(&self, _bound: RenderBound) -> SizeHint {
+        SizeHint::default()
     }
 }
 
@@ -175,11 +259,8 @@ where
         self.read().unwrap().render(ctx)
     }
 
-    // XXX TODO need to replace growth_policy with fn get_bounds(&self, bounds: Bounds) -> Bounds
-    // Must return something that fits within the given bounds
-    // This is needed for layout, to propagate back up minimum sizes from children
-    fn growth_policy(&self) -> FullGrowthPolicy {
-        self.read().unwrap().growth_policy()
+    fn size_hint(&self, bound: RenderBound) -> SizeHint {
+        self.read().unwrap().size_hint(bound)
     }
 
     fn name(&self) -> Option<N> {
@@ -198,6 +279,15 @@ where
     fn handle_input(&mut self, _ctx: &EventContext<N>, _event: &InputEvent) -> ShouldPropagate {
         ShouldPropagate::Continue
     }
+    fn handle_action(&mut self, _ctx: &EventContext<N>, _action: &'static str) {}
+    // Called as the executor's Keymap collects or resolves a multi-key sequence
+    fn handle_pending_keys(&mut self, _keys: &[KeyEvent]) {}
     fn handle_resize(&mut self, Size) {}
-    fn style(&self, ContentID<N>) -> (Option<Color>, Option<Color>);
+    // Apps that return a Theme get their styles from it, and reloaded when its file changes
+    fn theme(&self) -> Option<&Theme> {
+        None
+    }
+    fn style(&self, cid: ContentID<N>) -> Style {
+        self.theme().map(|theme| theme.style(cid)).unwrap_or_default()
+    }
 }
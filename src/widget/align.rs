@@ -0,0 +1,133 @@
+use ir::Align as HAlign;
+use {Name, Pos, RenderBound, RenderContext, SizeHint, SizeRange, TextBlock, Widget};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum VAlign {
+    #[default]
+    Top,
+    Middle,
+    Bottom,
+}
+
+// Renders a child at its preferred size and places it within the bound, filling the rest with
+// blanks of the "Blank" class. A child with no preference along an axis fills it.
+#[derive(Debug)]
+pub struct Align<'a, N>
+where
+    N: 'a + Name,
+{
+    child: &'a dyn Widget<N>,
+    horizontal: HAlign,
+    vertical: VAlign,
+    class: &'static str,
+}
+
+impl<'a, N> Align<'a, N>
+where
+    N: 'a + Name,
+{
+    pub fn new(child: &'a dyn Widget<N>, horizontal: HAlign, vertical: VAlign) -> Self {
+        let class = "Blank";
+        Self {
+            child,
+            horizontal,
+            vertical,
+            class,
+        }
+    }
+    pub fn center(child: &'a dyn Widget<N>) -> Self {
+        Self::new(child, HAlign::Center, VAlign::Middle)
+    }
+    pub fn with_class(mut self, class: &'static str) -> Self {
+        self.class = class;
+        self
+    }
+}
+
+// How much of `available` a child with this range gets
+pub(crate) fn fit(range: SizeRange, available: usize) -> usize {
+    match range.preferred {
+        0 => available,
+        size => size.min(available),
+    }
+}
+
+impl<'a, N> Widget<N> for Align<'a, N>
+where
+    N: 'a + Name,
+{
+    fn name(&self) -> Option<N> {
+        self.child.name()
+    }
+    fn render(&self, ctx: RenderContext<N>) -> TextBlock<N> {
+        let width = ctx.bound().width.expect("Align without width constraint");
+        let height = ctx.bound().height.expect("Align without height constraint");
+        let background = ctx.clip_lines(self.class, vec![]);
+        let cols = fit(
+            self.child
+                .size_hint(RenderBound::new(None, Some(height)))
+                .width,
+            width,
+        );
+        let rows = fit(
+            self.child
+                .size_hint(RenderBound::new(Some(cols), None))
+                .height,
+            height,
+        );
+        if cols == 0 || rows == 0 {
+            return background;
+        }
+        let col = match self.horizontal {
+            HAlign::Left => 0,
+            HAlign::Center => (width - cols) / 2,
+            HAlign::Right => width - cols,
+        };
+        let row = match self.vertical {
+            VAlign::Top => 0,
+            VAlign::Middle => (height - rows) / 2,
+            VAlign::Bottom => height - rows,
+        };
+        let child = ctx.render_sized(RenderBound::new(Some(cols), Some(rows)), self.child);
+        background.overlay(child, Pos::new(col, row))
+    }
+    // Needs the child's min, but is happy to take any room beyond that
+    fn size_hint(&self, bound: RenderBound) -> SizeHint {
+        let hint = self.child.size_hint(bound);
+        let open = |r: SizeRange| SizeRange::new(r.min, r.preferred, None);
+        SizeHint::new(open(hint.width), open(hint.height))
+    }
+    fn widget_type(&self) -> &'static str {
+        "Align"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Align, VAlign};
+    use ir::Align as HAlign;
+    use widget::{Constraint, Linear, SimpleInput};
+    use {Pos, RenderBound, RenderContext, Widget};
+
+    #[test]
+    fn places_child() {
+        let input = SimpleInput::new(1);
+        let sized: Linear<u8> = Linear::hbox(vec![&input]).constrain(0, Constraint::fixed(4));
+        let bound = RenderBound::new(Some(10), Some(5));
+        let centered = Align::center(&sized);
+        let block = centered.render(RenderContext::from_widget(bound, &centered));
+        assert_eq!(block.get_cursor(1), Some(Pos::new(3, 2)));
+        let mut classes: Vec<&str> = block.lines[2].segments.iter().map(|s| s.id.class).collect();
+        classes.dedup();
+        assert_eq!(classes, vec!["Blank", "Buffer", "Blank"]);
+
+        let corner = Align::new(&sized, HAlign::Right, VAlign::Bottom);
+        let block = corner.render(RenderContext::from_widget(bound, &corner));
+        assert_eq!(block.get_cursor(1), Some(Pos::new(6, 4)));
+
+        // SimpleInput has no preferred width, so it stretches across
+        let stretched = Align::new(&input, HAlign::Right, VAlign::Middle);
+        let block = stretched.render(RenderContext::from_widget(bound, &stretched));
+        assert_eq!(block.get_cursor(1), Some(Pos::new(0, 2)));
+    }
+}
@@ -0,0 +1,213 @@
+use ir::{sanitize, text_width};
+use {
+    Align, Name, Overflow, Pos, RenderBound, RenderContext, SizeHint, SizeRange, TextBlock, Widget,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BorderStyle {
+    #[default]
+    Single,
+    Double,
+    Rounded,
+    Thick,
+    Ascii,
+}
+
+impl BorderStyle {
+    // Top left, top right, bottom left, bottom right, horizontal, vertical
+    fn glyphs(self) -> [char; 6] {
+        match self {
+            BorderStyle::Single => ['┌', '┐', '└', '┘', '─', '│'],
+            BorderStyle::Double => ['╔', '╗', '╚', '╝', '═', '║'],
+            BorderStyle::Rounded => ['╭', '╮', '╰', '╯', '─', '│'],
+            BorderStyle::Thick => ['┏', '┓', '┗', '┛', '━', '┃'],
+            BorderStyle::Ascii => ['+', '+', '+', '+', '-', '|'],
+        }
+    }
+}
+
+// Draws a border around a child, with titles set into the top edge and footers into the
+// bottom. The border, titles and footers have the "Border", "Title" and "Footer" classes, and
+// take the child's name so they can be styled along with it.
+#[derive(Debug)]
+pub struct Border<'a, N>
+where
+    N: 'a + Name,
+{
+    child: &'a dyn Widget<N>,
+    style: BorderStyle,
+    titles: Vec<(Align, String)>,
+    footers: Vec<(Align, String)>,
+}
+
+impl<'a, N> Border<'a, N>
+where
+    N: 'a + Name,
+{
+    pub fn new(child: &'a dyn Widget<N>) -> Self {
+        let style = BorderStyle::default();
+        let titles = vec![];
+        let footers = vec![];
+        Self {
+            child,
+            style,
+            titles,
+            footers,
+        }
+    }
+    pub fn with_style(mut self, style: BorderStyle) -> Self {
+        self.style = style;
+        self
+    }
+    pub fn title<S: Into<String>>(mut self, align: Align, title: S) -> Self {
+        self.titles.push((align, title.into()));
+        self
+    }
+    pub fn footer<S: Into<String>>(mut self, align: Align, footer: S) -> Self {
+        self.footers.push((align, footer.into()));
+        self
+    }
+}
+
+// Sets labels into a border row, cut short with an ellipsis when the edge is too narrow
+fn label_row<N: Name>(
+    ctx: &RenderContext<N>,
+    mut block: TextBlock<N>,
+    class: &'static str,
+    labels: &[(Align, String)],
+    row: usize,
+) -> TextBlock<N> {
+    let inner = block.size().cols.saturating_sub(2);
+    for (align, label) in labels {
+        // Measured as it will be drawn, since control characters come out wider
+        let label = sanitize(&format!(" {} ", label), 0, ctx.tab_width());
+        let len = text_width(&label).min(inner);
+        if len == 0 {
+            continue;
+        }
+        let col = 1 + match align {
+            Align::Left => 0,
+            Align::Center => (inner - len) / 2,
+            Align::Right => inner - len,
+        };
+        let label = ctx
+            .with_bound(RenderBound::new(Some(len), Some(1)))
+            .with_overflow(Overflow::Ellipsis)
+            .clip_lines(class, vec![label]);
+        block = block.overlay(label, Pos::new(col, row));
+    }
+    block
+}
+
+impl<'a, N> Widget<N> for Border<'a, N>
+where
+    N: 'a + Name,
+{
+    fn name(&self) -> Option<N> {
+        self.child.name()
+    }
+    fn render(&self, ctx: RenderContext<N>) -> TextBlock<N> {
+        let width = ctx.bound().width.expect("Border without width constraint");
+        let height = ctx
+            .bound()
+            .height
+            .expect("Border without height constraint");
+        if width < 2 || height < 2 {
+            return ctx.clip_lines("Border", vec![]);
+        }
+        let (inner_cols, inner_rows) = (width - 2, height - 2);
+        let [top_left, top_right, bottom_left, bottom_right, horizontal, vertical] =
+            self.style.glyphs();
+        let edge = |left: char, right: char| {
+            let mut line = left.to_string();
+            line.push_str(&horizontal.to_string().repeat(inner_cols));
+            line.push(right);
+            line
+        };
+        let mut side = vertical.to_string();
+        side.push_str(&" ".repeat(inner_cols));
+        side.push(vertical);
+        let lines: Vec<String> = Some(edge(top_left, top_right))
+            .into_iter()
+            .chain(vec![side; inner_rows])
+            .chain(Some(edge(bottom_left, bottom_right)))
+            .collect();
+        let block = ctx.clip_lines("Border", lines);
+        let block = label_row(&ctx, block, "Title", &self.titles, 0);
+        let mut block = label_row(&ctx, block, "Footer", &self.footers, height - 1);
+        if inner_cols > 0 && inner_rows > 0 {
+            let bound = RenderBound::new(Some(inner_cols), Some(inner_rows));
+            let child = ctx.render_sized(bound, self.child);
+            block = block.overlay(child, Pos::new(1, 1));
+        }
+        block
+    }
+    fn size_hint(&self, bound: RenderBound) -> SizeHint {
+        let inner = RenderBound::new(
+            bound.width.map(|w| w.saturating_sub(2)),
+            bound.height.map(|h| h.saturating_sub(2)),
+        );
+        let hint = self.child.size_hint(inner);
+        let grow = |r: SizeRange| SizeRange::new(r.min + 2, r.preferred + 2, r.max.map(|m| m + 2));
+        SizeHint::new(grow(hint.width), grow(hint.height))
+    }
+    fn widget_type(&self) -> &'static str {
+        "Border"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Border, BorderStyle};
+    use executor::Event;
+    use test_util::{click, event_context, row_text};
+    use widget::SimpleInput;
+    use {Align, AppEvent, Pos, RenderBound, RenderContext, Widget};
+
+    #[test]
+    fn titles_and_footers() {
+        let input = SimpleInput::new(1);
+        let border = Border::new(&input)
+            .with_style(BorderStyle::Rounded)
+            .title(Align::Left, "In")
+            .title(Align::Right, "1")
+            .footer(Align::Center, "a long footer");
+        let bound = RenderBound::new(Some(12), Some(3));
+        let block = border.render(RenderContext::from_widget(bound, &border));
+        assert_eq!(row_text(&block, 0), "╭ In ─── 1 ╮");
+        assert_eq!(row_text(&block, 1), "│          │");
+        assert_eq!(row_text(&block, 2), "╰ a long f…╯");
+        let classes: Vec<&str> = block.lines[0].segments.iter().map(|s| s.id.class).collect();
+        assert_eq!(
+            classes,
+            vec!["Border", "Title", "Border", "Title", "Border"]
+        );
+    }
+
+    #[test]
+    fn control_characters_in_titles() {
+        let input = SimpleInput::new(1);
+        let border = Border::new(&input).title(Align::Left, "a\x1bb");
+        let bound = RenderBound::new(Some(10), Some(3));
+        let block = border.render(RenderContext::from_widget(bound, &border));
+        assert_eq!(row_text(&block, 0), "┌ a^[b ──┐");
+    }
+
+    #[test]
+    fn child_offsets() {
+        let input = SimpleInput::new(1);
+        let border = Border::new(&input).with_style(BorderStyle::Ascii);
+        let bound = RenderBound::new(Some(6), Some(3));
+        let block = border.render(RenderContext::from_widget(bound, &border));
+        assert_eq!(row_text(&block, 0), "+----+");
+        assert_eq!(block.get_cursor(1), Some(Pos::new(1, 1)));
+
+        let (ctx, receiver) = event_context();
+        block.handle_mouse(&ctx, click(0, 1));
+        block.handle_mouse(&ctx, click(3, 0));
+        assert!(receiver.try_iter().next().is_none());
+        block.handle_mouse(&ctx, click(4, 1));
+        let events: Vec<Event<u8>> = receiver.try_iter().collect();
+        assert_eq!(events, vec![Event::App(AppEvent::SetFocus(1))]);
+    }
+}
@@ -0,0 +1,246 @@
+use std::fmt;
+
+use widget::layout::{ranges, sizes, Constraint};
+use {Name, Pos, RenderBound, RenderContext, SizeHint, SizeRange, TextBlock, Widget};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cell {
+    col: usize,
+    row: usize,
+    col_span: usize,
+    row_span: usize,
+}
+
+// Lays children out on column and row tracks, sized like the children of a Linear. Auto
+// tracks go by the children that sit in them alone; spanning children just take the room
+// their tracks end up with.
+pub struct Grid<'a, N>
+where
+    N: 'a + Name,
+{
+    cols: Vec<Constraint>,
+    rows: Vec<Constraint>,
+    col_gap: usize,
+    row_gap: usize,
+    cells: Vec<(&'a dyn Widget<N>, Cell)>,
+}
+
+impl<'a, N> Grid<'a, N>
+where
+    N: 'a + Name,
+{
+    pub fn new(cols: Vec<Constraint>, rows: Vec<Constraint>) -> Self {
+        let col_gap = 0;
+        let row_gap = 0;
+        let cells = vec![];
+        Self {
+            cols,
+            rows,
+            col_gap,
+            row_gap,
+            cells,
+        }
+    }
+    // Gaps between tracks are left blank, with the "Gap" class
+    pub fn with_gaps(mut self, col_gap: usize, row_gap: usize) -> Self {
+        self.col_gap = col_gap;
+        self.row_gap = row_gap;
+        self
+    }
+    pub fn add(self, widget: &'a dyn Widget<N>, col: usize, row: usize) -> Self {
+        self.add_spanning(widget, col, row, 1, 1)
+    }
+    pub fn add_spanning(
+        mut self,
+        widget: &'a dyn Widget<N>,
+        col: usize,
+        row: usize,
+        col_span: usize,
+        row_span: usize,
+    ) -> Self {
+        assert!(col_span > 0 && row_span > 0, "Grid cell spanning no tracks");
+        assert!(
+            col + col_span <= self.cols.len() && row + row_span <= self.rows.len(),
+            "Grid cell outside the grid"
+        );
+        let cell = Cell {
+            col,
+            row,
+            col_span,
+            row_span,
+        };
+        self.cells.push((widget, cell));
+        self
+    }
+    fn col_hints(&self) -> Vec<SizeRange> {
+        let bound = RenderBound::new(None, None);
+        let mut hints = vec![SizeRange::default(); self.cols.len()];
+        for (widget, cell) in &self.cells {
+            if cell.col_span == 1 {
+                let hint = widget.size_hint(bound).width;
+                hints[cell.col] = widest(hints[cell.col], hint);
+            }
+        }
+        hints
+    }
+    fn row_hints(&self, col_sizes: Option<&[usize]>) -> Vec<SizeRange> {
+        let mut hints = vec![SizeRange::default(); self.rows.len()];
+        for (widget, cell) in &self.cells {
+            if cell.row_span == 1 {
+                let width =
+                    col_sizes.map(|sizes| span(sizes, self.col_gap, cell.col, cell.col_span));
+                let hint = widget.size_hint(RenderBound::new(width, None)).height;
+                hints[cell.row] = widest(hints[cell.row], hint);
+            }
+        }
+        hints
+    }
+}
+
+// Grows a track's range to fit another child's hint
+fn widest(a: SizeRange, b: SizeRange) -> SizeRange {
+    let max = match (a.max, b.max) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        _ => None,
+    };
+    SizeRange::new(a.min.max(b.min), a.preferred.max(b.preferred), max)
+}
+
+// Where each track starts, counting the gaps before it
+fn starts(sizes: &[usize], gap: usize) -> Vec<usize> {
+    sizes
+        .iter()
+        .scan(0, |next, size| {
+            let start = *next;
+            *next += size + gap;
+            Some(start)
+        }).collect()
+}
+
+// The length of `count` tracks from `first`, including the gaps between them
+fn span(sizes: &[usize], gap: usize, first: usize, count: usize) -> usize {
+    sizes[first..first + count].iter().sum::<usize>() + gap * (count - 1)
+}
+
+// The range of a whole axis: its tracks plus its gaps
+fn total_range(ranges: &[SizeRange], gap: usize) -> SizeRange {
+    let gaps = gap * ranges.len().saturating_sub(1);
+    SizeRange::new(
+        ranges.iter().map(|r| r.min).sum::<usize>() + gaps,
+        ranges.iter().map(|r| r.preferred).sum::<usize>() + gaps,
+        ranges
+            .iter()
+            .map(|r| r.max)
+            .sum::<Option<usize>>()
+            .map(|max| max + gaps),
+    )
+}
+
+impl<'a, N> Widget<N> for Grid<'a, N>
+where
+    N: 'a + Name,
+{
+    fn name(&self) -> Option<N> {
+        None
+    }
+    fn render(&self, ctx: RenderContext<N>) -> TextBlock<N> {
+        let width = ctx.bound().width.expect("Grid without width constraint");
+        let height = ctx.bound().height.expect("Grid without height constraint");
+        let col_gaps = self.col_gap * self.cols.len().saturating_sub(1);
+        let row_gaps = self.row_gap * self.rows.len().saturating_sub(1);
+        let col_sizes = sizes(
+            &self.cols,
+            &self.col_hints(),
+            width.saturating_sub(col_gaps),
+        );
+        let row_sizes = sizes(
+            &self.rows,
+            &self.row_hints(Some(&col_sizes)),
+            height.saturating_sub(row_gaps),
+        );
+        let col_starts = starts(&col_sizes, self.col_gap);
+        let row_starts = starts(&row_sizes, self.row_gap);
+        // Children are painted over a blank background, which also fills the gaps
+        let background = ctx.clip_lines("Gap", vec![]);
+        self.cells.iter().fold(background, |block, (widget, cell)| {
+            let cols = span(&col_sizes, self.col_gap, cell.col, cell.col_span);
+            let rows = span(&row_sizes, self.row_gap, cell.row, cell.row_span);
+            if cols == 0 || rows == 0 {
+                return block;
+            }
+            let child = ctx.render_sized(RenderBound::new(Some(cols), Some(rows)), *widget);
+            block.overlay(child, Pos::new(col_starts[cell.col], row_starts[cell.row]))
+        })
+    }
+    fn size_hint(&self, _bound: RenderBound) -> SizeHint {
+        let cols = ranges(&self.cols, &self.col_hints(), None);
+        let rows = ranges(&self.rows, &self.row_hints(None), None);
+        SizeHint::new(
+            total_range(&cols, self.col_gap),
+            total_range(&rows, self.row_gap),
+        )
+    }
+    fn widget_type(&self) -> &'static str {
+        "Grid"
+    }
+}
+
+impl<'a, N> fmt::Debug for Grid<'a, N>
+where
+    N: 'a + Name,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Grid")
+            .field("cols", &self.cols)
+            .field("rows", &self.rows)
+            .field("col_gap", &self.col_gap)
+            .field("row_gap", &self.row_gap)
+            .field("cells", &self.cells)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Grid;
+    use executor::Event;
+    use test_util::{click, event_context};
+    use widget::layout::Constraint;
+    use widget::SimpleInput;
+    use {AppEvent, Pos, RenderBound, RenderContext, Size, Widget};
+
+    #[test]
+    fn spans_and_gaps() {
+        let first = SimpleInput::new(1);
+        let second = SimpleInput::new(2);
+        let wide = SimpleInput::new(3);
+        let grid = Grid::new(
+            vec![Constraint::fixed(4), Constraint::weight(1)],
+            vec![Constraint::fixed(1), Constraint::fixed(1)],
+        ).with_gaps(1, 1)
+        .add(&first, 0, 0)
+        .add(&second, 1, 0)
+        .add_spanning(&wide, 0, 1, 2, 1);
+        let bound = RenderBound::new(Some(10), Some(3));
+        let block = grid.render(RenderContext::from_widget(bound, &grid));
+        assert_eq!(block.size(), Size::new(10, 3));
+        assert_eq!(block.get_cursor(1), Some(Pos::new(0, 0)));
+        assert_eq!(block.get_cursor(2), Some(Pos::new(5, 0)));
+        assert_eq!(block.get_cursor(3), Some(Pos::new(0, 2)));
+        assert_eq!(block.focus_order(), vec![1, 2, 3]);
+
+        let (ctx, receiver) = event_context();
+        // The gap row takes no hits, and the spanning input takes them across both columns
+        block.handle_mouse(&ctx, click(8, 1));
+        block.handle_mouse(&ctx, click(8, 2));
+        block.handle_mouse(&ctx, click(6, 0));
+        let events: Vec<Event<u8>> = receiver.try_iter().collect();
+        assert_eq!(
+            events,
+            vec![
+                Event::App(AppEvent::SetFocus(3)),
+                Event::App(AppEvent::SetFocus(2)),
+            ]
+        );
+    }
+}
@@ -1,13 +1,87 @@
+use std::cmp::Reverse;
 use std::fmt;
-use std::iter::repeat;
 
-use {Direction, GrowthPolicy, Name, RenderContext, TextBlock, Widget};
+use {Direction, Name, RenderBound, RenderContext, SizeHint, SizeRange, TextBlock, Widget};
+
+// How much of a Linear's length a child takes
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Length {
+    // Whatever the child's size hint asks for, sharing spare room like a weight of 1
+    #[default]
+    Auto,
+    Fixed(usize),
+    // Rounded so that percentages adding up to 100 fill the layout exactly
+    Percent(usize),
+    // A share of the room the other children leave
+    Weight(usize),
+}
+
+#[derive(Default, Debug, PartialEq, Clone, Copy)]
+pub struct Constraint {
+    pub length: Length,
+    pub min: Option<usize>,
+    pub max: Option<usize>,
+}
+
+impl Constraint {
+    pub fn new(length: Length) -> Self {
+        let min = None;
+        let max = None;
+        Self { length, min, max }
+    }
+    pub fn fixed(cells: usize) -> Self {
+        Self::new(Length::Fixed(cells))
+    }
+    pub fn percent(percent: usize) -> Self {
+        Self::new(Length::Percent(percent))
+    }
+    pub fn weight(weight: usize) -> Self {
+        Self::new(Length::Weight(weight))
+    }
+    pub fn min(mut self, min: usize) -> Self {
+        self.min = Some(min);
+        self
+    }
+    pub fn max(mut self, max: usize) -> Self {
+        self.max = Some(max);
+        self
+    }
+    fn weight_of(&self) -> usize {
+        match self.length {
+            Length::Auto => 1,
+            Length::Weight(weight) => weight,
+            Length::Fixed(_) | Length::Percent(_) => 0,
+        }
+    }
+    // Where min and max disagree with the child's hint, they win
+    fn limit(&self, range: SizeRange) -> SizeRange {
+        let clamp = |size: usize| {
+            let size = self.min.map_or(size, |min| size.max(min));
+            self.max.map_or(size, |max| size.min(max))
+        };
+        SizeRange {
+            min: clamp(range.min),
+            preferred: clamp(range.preferred),
+            max: range.max.map(clamp).or(self.max),
+        }
+    }
+    // `percent_size` is only known while rendering
+    fn range(&self, hint: SizeRange, percent_size: Option<usize>) -> SizeRange {
+        match (self.length, percent_size) {
+            (Length::Auto, _) => self.limit(hint),
+            (Length::Fixed(cells), _) => self.limit(SizeRange::fixed(cells)),
+            (Length::Percent(_), Some(size)) => self.limit(SizeRange::fixed(size)),
+            (Length::Percent(_), None) | (Length::Weight(_), _) => self.limit(SizeRange::default()),
+        }
+    }
+}
 
 pub struct Linear<'a, N>
 where
     N: 'a + Name,
 {
     widgets: Vec<&'a dyn Widget<N>>,
+    constraints: Vec<Constraint>,
     direction: Direction,
 }
 
@@ -16,7 +90,12 @@ where
     N: 'a + Name,
 {
     pub fn new(direction: Direction, widgets: Vec<&'a dyn Widget<N>>) -> Self {
-        Self { direction, widgets }
+        let constraints = vec![Constraint::default(); widgets.len()];
+        Self {
+            direction,
+            widgets,
+            constraints,
+        }
     }
     pub fn hbox(widgets: Vec<&'a dyn Widget<N>>) -> Self {
         Self::new(Direction::Horizontal, widgets)
@@ -24,6 +103,11 @@ where
     pub fn vbox(widgets: Vec<&'a dyn Widget<N>>) -> Self {
         Self::new(Direction::Vertical, widgets)
     }
+    // Sizes the child at `index`, which otherwise goes by its own size hint
+    pub fn constrain(mut self, index: usize, constraint: Constraint) -> Self {
+        self.constraints[index] = constraint;
+        self
+    }
 }
 
 impl<'a, N> Widget<N> for Linear<'a, N>
@@ -36,50 +120,69 @@ where
 
     fn render(&self, ctx: RenderContext<N>) -> TextBlock<N> {
         let dir = self.direction;
-        // Clippy complains about a complex type here; I'm not convinced it's an improvement.
-        type Segments<T> = (Vec<(usize, T)>, Vec<(usize, T)>);
-        let (fixed, greedy): Segments<&dyn Widget<N>> = self
-            .widgets
-            .clone()
-            .into_iter()
-            .enumerate()
-            .partition(|(_, w)| {
-                w.growth_policy().in_direction(self.direction) == GrowthPolicy::FixedSize
-            });
-        let mut remaining_size = ctx
+        let total = ctx
             .bound()
             .in_direction(dir)
             .expect("Linear layout without on-axis constraint");
-        let fixed_constraint = ctx
-            .bound()
+        ctx.bound()
             .against_direction(dir)
             .expect("Linear layout without off-axis constraint");
-        let greedy_count = greedy.len();
         let free_bound = ctx.bound().free_direction(dir);
-        let mut blocks: Vec<(usize, TextBlock<N>)> = fixed
-            .into_iter()
-            .map(|(i, w)| {
-                // XXX TODO Maybe should be fetching bounds from children?
-                let b = ctx.render_sized(free_bound, w);
-                assert_eq!(b.size().against_direction(dir), fixed_constraint);
-                remaining_size -= b.size().in_direction(dir);
-                (i, b)
-            }).collect();
-        // XXX TODO UPDATE THIS when we get bounds from children
-        let chunk_size = remaining_size / greedy_count;
-        let sizes = repeat(chunk_size + 1)
-            .take(remaining_size % greedy_count)
-            .chain(repeat(chunk_size));
-        blocks.extend(greedy.into_iter().zip(sizes).map(|((i, w), size)| {
-            let bound = free_bound.constrain_direction(dir, size);
-            let b = ctx.render_sized(bound, w);
-            (i, b)
-        }));
-        blocks.sort_by_key(|a| a.0);
-        let mut blocks = blocks.into_iter().map(|(_, b)| b);
+        let hints: Vec<SizeRange> = self
+            .widgets
+            .iter()
+            .map(|w| w.size_hint(free_bound).in_direction(dir))
+            .collect();
+        let sizes = sizes(&self.constraints, &hints, total);
+        let used: usize = sizes.iter().sum();
+        let mut blocks: Vec<TextBlock<N>> = self
+            .widgets
+            .iter()
+            .zip(sizes)
+            .filter(|(_, size)| *size > 0)
+            .map(|(w, size)| ctx.render_sized(free_bound.constrain_direction(dir, size), *w))
+            .collect();
+        if used < total || blocks.is_empty() {
+            // Every child is at its max, so pad out the rest
+            let filler = ctx.with_bound(free_bound.constrain_direction(dir, total - used));
+            blocks.push(filler.clip_lines("Filler", vec![]));
+        }
+        let mut blocks = blocks.into_iter();
         let init = blocks.next().expect("Linear layout with no children");
         blocks.fold(init, |acc, b| acc.concat_dir(dir, b))
     }
+    fn size_hint(&self, bound: RenderBound) -> SizeHint {
+        let dir = self.direction;
+        let hints: Vec<SizeHint> = self
+            .widgets
+            .iter()
+            .map(|w| w.size_hint(bound.free_direction(dir)))
+            .collect();
+        let along: Vec<SizeRange> = hints.iter().map(|h| h.in_direction(dir)).collect();
+        let along = ranges(&self.constraints, &along, None);
+        let across: Vec<SizeRange> = hints
+            .iter()
+            .map(|h| h.in_direction(dir.against()))
+            .collect();
+        let along = SizeRange::new(
+            along.iter().map(|r| r.min).sum(),
+            along.iter().map(|r| r.preferred).sum(),
+            along.iter().map(|r| r.max).sum(),
+        );
+        let across = SizeRange::new(
+            across.iter().map(|r| r.min).max().unwrap_or(0),
+            across.iter().map(|r| r.preferred).max().unwrap_or(0),
+            across
+                .iter()
+                .map(|r| r.max)
+                .collect::<Option<Vec<usize>>>()
+                .map(|maxes| maxes.into_iter().max().unwrap_or(0)),
+        );
+        match dir {
+            Direction::Vertical => SizeHint::new(across, along),
+            Direction::Horizontal => SizeHint::new(along, across),
+        }
+    }
     fn widget_type(&self) -> &'static str {
         "Linear"
     }
@@ -93,6 +196,166 @@ where
         f.debug_struct("Linear")
             .field("direction", &self.direction)
             .field("widgets", &self.widgets)
+            .field("constraints", &self.constraints)
             .finish()
     }
 }
+
+// The range each child or track can take, with percentages resolved once `total` is known
+pub(crate) fn ranges(
+    constraints: &[Constraint],
+    hints: &[SizeRange],
+    total: Option<usize>,
+) -> Vec<SizeRange> {
+    let mut percents: Vec<usize> = constraints
+        .iter()
+        .map(|c| match c.length {
+            Length::Percent(percent) => percent,
+            _ => 0,
+        }).collect();
+    let unclaimed = 100usize.saturating_sub(percents.iter().sum());
+    percents.push(unclaimed);
+    let percent_sizes = total.map(|total| apportion(total, &percents));
+    constraints
+        .iter()
+        .zip(hints)
+        .enumerate()
+        .map(|(i, (c, hint))| c.range(*hint, percent_sizes.as_ref().map(|sizes| sizes[i])))
+        .collect()
+}
+
+pub(crate) fn sizes(constraints: &[Constraint], hints: &[SizeRange], total: usize) -> Vec<usize> {
+    let ranges = ranges(constraints, hints, Some(total));
+    let weights: Vec<usize> = constraints.iter().map(|c| c.weight_of()).collect();
+    distribute(total, &ranges, &weights)
+}
+
+// Splits `total` between children: each gets its min, then room goes to those below their
+// preferred size, and then to those below their max, in proportion to their weights. When
+// even the mins don't fit, the last children lose out. Room is left over only when every
+// child with a weight is at its max.
+fn distribute(total: usize, ranges: &[SizeRange], weights: &[usize]) -> Vec<usize> {
+    let mut remaining = total;
+    let mut sizes: Vec<usize> = ranges
+        .iter()
+        .map(|r| {
+            let size = r.min.min(remaining);
+            remaining -= size;
+            size
+        }).collect();
+    let preferred: Vec<Option<usize>> = ranges.iter().map(|r| Some(r.preferred)).collect();
+    remaining = grow(&mut sizes, remaining, &preferred, weights);
+    let max: Vec<Option<usize>> = ranges.iter().map(|r| r.max).collect();
+    grow(&mut sizes, remaining, &max, weights);
+    sizes
+}
+
+// Shares `remaining` between the sizes still under their limits. Returns whatever couldn't
+// be handed out.
+fn grow(
+    sizes: &mut [usize],
+    mut remaining: usize,
+    limits: &[Option<usize>],
+    weights: &[usize],
+) -> usize {
+    loop {
+        let open: Vec<usize> = (0..sizes.len())
+            .filter(|&i| weights[i] > 0 && limits[i].filter(|&limit| sizes[i] >= limit).is_none())
+            .collect();
+        if open.is_empty() || remaining == 0 {
+            return remaining;
+        }
+        let open_weights: Vec<usize> = open.iter().map(|&i| weights[i]).collect();
+        for (&i, want) in open.iter().zip(apportion(remaining, &open_weights)) {
+            let given = limits[i].map_or(want, |limit| want.min(limit - sizes[i]));
+            sizes[i] += given;
+            remaining -= given;
+        }
+    }
+}
+
+// Splits `total` in proportion to `weights`, handing the cells lost to rounding down to the
+// largest remainders, earlier parts first, so the parts always add up to `total`
+fn apportion(total: usize, weights: &[usize]) -> Vec<usize> {
+    let weight_sum: usize = weights.iter().sum();
+    if weight_sum == 0 {
+        return vec![0; weights.len()];
+    }
+    let mut parts: Vec<usize> = weights.iter().map(|w| total * w / weight_sum).collect();
+    let short = total - parts.iter().sum::<usize>();
+    let mut order: Vec<usize> = (0..weights.len()).collect();
+    order.sort_by_key(|&i| Reverse(total * weights[i] % weight_sum));
+    for &i in order.iter().take(short) {
+        parts[i] += 1;
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apportion, distribute, sizes, Constraint, Linear};
+    use widget::{Line, Log};
+    use {RenderBound, RenderContext, Size, SizeRange, Widget};
+
+    #[test]
+    fn distribution() {
+        let greedy = SizeRange::default();
+        let ones = [1, 1, 1];
+        assert_eq!(
+            distribute(10, &[greedy, greedy, greedy], &ones),
+            vec![4, 3, 3]
+        );
+        let fixed = SizeRange::fixed(1);
+        assert_eq!(
+            distribute(10, &[greedy, fixed, greedy], &ones),
+            vec![5, 1, 4]
+        );
+        let capped = SizeRange::new(2, 4, Some(5));
+        assert_eq!(distribute(10, &[capped, greedy], &ones[..2]), vec![5, 5]);
+        assert_eq!(distribute(6, &[capped, greedy], &ones[..2]), vec![5, 1]);
+        assert_eq!(distribute(3, &[capped, capped], &ones[..2]), vec![2, 1]);
+        assert_eq!(distribute(20, &[capped, fixed], &ones[..2]), vec![5, 1]);
+        assert_eq!(apportion(10, &[1, 1, 1]), vec![4, 3, 3]);
+        assert_eq!(apportion(10, &[33, 33, 34]), vec![3, 3, 4]);
+    }
+
+    #[test]
+    fn constraints() {
+        let line = Line::vertical();
+        let greedy = SizeRange::default();
+        let hbox: Linear<u8> = Linear::hbox(vec![&line, &line, &line])
+            .constrain(0, Constraint::percent(25))
+            .constrain(2, Constraint::weight(2));
+        assert_eq!(sizes(&hbox.constraints, &[greedy; 3], 41), vec![10, 10, 21]);
+        let thirds: Linear<u8> = Linear::hbox(vec![&line, &line, &line])
+            .constrain(0, Constraint::percent(33))
+            .constrain(1, Constraint::percent(33))
+            .constrain(2, Constraint::percent(34));
+        assert_eq!(sizes(&thirds.constraints, &[greedy; 3], 10), vec![3, 3, 4]);
+        let capped: Linear<u8> = Linear::hbox(vec![&line, &line])
+            .constrain(0, Constraint::weight(1).max(3))
+            .constrain(1, Constraint::weight(1));
+        assert_eq!(sizes(&capped.constraints, &[greedy; 2], 10), vec![3, 7]);
+        let squeezed: Linear<u8> = Linear::hbox(vec![&line, &line])
+            .constrain(0, Constraint::fixed(4))
+            .constrain(1, Constraint::weight(1).min(2));
+        assert_eq!(sizes(&squeezed.constraints, &[greedy; 2], 3), vec![3, 0]);
+        assert_eq!(sizes(&squeezed.constraints, &[greedy; 2], 9), vec![4, 5]);
+    }
+
+    #[test]
+    fn logs_keep_min_height() {
+        let log1: Log<u8> = Log::new(Some(1)).with_min_height(3);
+        let log2: Log<u8> = Log::new(Some(2));
+        let line = Line::horizontal();
+        let vbox = Linear::vbox(vec![&log1, &line, &log2]);
+        let hint = vbox.size_hint(RenderBound::new(Some(10), None));
+        assert_eq!(hint.height.min, 4);
+        for &(rows, line_row) in &[(4, 3), (7, 5)] {
+            let bound = RenderBound::new(Some(10), Some(rows));
+            let block = vbox.render(RenderContext::from_widget(bound, &vbox));
+            assert_eq!(block.size(), Size::new(10, rows));
+            assert_eq!(block.lines[line_row].segments[0].text, "─".repeat(10));
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use std::iter::repeat;
-use {Direction, FullGrowthPolicy, Name, RenderContext, TextBlock, Widget};
+use {Direction, Name, RenderBound, RenderContext, SizeHint, TextBlock, Widget};
 
 #[derive(Debug)]
 pub struct Line {
@@ -40,10 +40,10 @@ where
             }
         }
     }
-    fn growth_policy(&self) -> FullGrowthPolicy {
+    fn size_hint(&self, _bound: RenderBound) -> SizeHint {
         match self.direction {
-            Direction::Horizontal => FullGrowthPolicy::fixed_height(),
-            Direction::Vertical => FullGrowthPolicy::fixed_width(),
+            Direction::Horizontal => SizeHint::fixed_height(1),
+            Direction::Vertical => SizeHint::fixed_width(1),
         }
     }
     fn name(&self) -> Option<N> {
@@ -53,3 +53,18 @@ where
         "Line"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Line;
+    use {RenderBound, RenderContext, TextBlock, Widget};
+
+    #[test]
+    fn content_ids_in_order() {
+        let line = Line::horizontal();
+        let bound = RenderBound::new(Some(3), Some(1));
+        let block: TextBlock<u8> = line.render(RenderContext::from_widget(bound, &line));
+        let id = block.lines[0].segments[0].id;
+        assert_eq!(id.as_tuple(), (None, "Line", "Horizontal"));
+    }
+}
@@ -1,5 +1,5 @@
 //use input::{MouseButton, MouseEvent};
-use {shared, Name, RenderContext, Shared, TextBlock, Widget};
+use {shared, Name, RenderBound, RenderContext, Shared, SizeHint, TextBlock, Widget, Wrap};
 
 #[derive(Debug, Default)]
 pub struct Log<N: Name> {
@@ -7,6 +7,8 @@ pub struct Log<N: Name> {
     pub scroll_pos: Shared<usize>,
     pub selected: Option<usize>,
     pub name: Option<N>,
+    pub wrap: Wrap,
+    pub min_height: usize,
 }
 
 impl<N> Log<N>
@@ -17,13 +19,22 @@ where
         let lines = vec![];
         let scroll_pos = shared(0);
         let selected = None;
+        let wrap = Wrap::default();
+        let min_height = 0;
         Log {
             lines,
             scroll_pos,
             selected,
             name,
+            wrap,
+            min_height,
         }
     }
+    // Layouts won't squeeze the log below this many rows
+    pub fn with_min_height(mut self, min_height: usize) -> Self {
+        self.min_height = min_height;
+        self
+    }
     pub fn log_msg(&mut self, msg: &str) {
         self.lines.push(msg.to_owned());
     }
@@ -38,7 +49,8 @@ where
     }
     fn render(&self, ctx: RenderContext<N>) -> TextBlock<N> {
         //let scroll_pos = self.scroll_pos.clone();
-        ctx.wrap_lines("Content", self.lines.clone())
+        ctx.with_wrap(self.wrap)
+            .wrap_lines("Content", self.lines.clone())
         /*.add_key_handler(
             None,
             Box::new(move |_ctx, e| {
@@ -60,6 +72,9 @@ where
             }),
         )*/
     }
+    fn size_hint(&self, _bound: RenderBound) -> SizeHint {
+        SizeHint::default().min_height(self.min_height)
+    }
     fn widget_type(&self) -> &'static str {
         "Log"
     }
@@ -0,0 +1,119 @@
+use {Name, Pos, RenderBound, RenderContext, SizeHint, SizeRange, TextBlock, Widget};
+
+// Insets a child, filling the space around it with blanks of the "Padding" class
+#[derive(Debug)]
+pub struct Padding<'a, N>
+where
+    N: 'a + Name,
+{
+    child: &'a dyn Widget<N>,
+    top: usize,
+    bottom: usize,
+    left: usize,
+    right: usize,
+    class: &'static str,
+}
+
+impl<'a, N> Padding<'a, N>
+where
+    N: 'a + Name,
+{
+    pub fn new(
+        child: &'a dyn Widget<N>,
+        top: usize,
+        right: usize,
+        bottom: usize,
+        left: usize,
+    ) -> Self {
+        let class = "Padding";
+        Self {
+            child,
+            top,
+            bottom,
+            left,
+            right,
+            class,
+        }
+    }
+    pub fn uniform(child: &'a dyn Widget<N>, padding: usize) -> Self {
+        Self::new(child, padding, padding, padding, padding)
+    }
+    pub fn with_class(mut self, class: &'static str) -> Self {
+        self.class = class;
+        self
+    }
+}
+
+impl<'a, N> Widget<N> for Padding<'a, N>
+where
+    N: 'a + Name,
+{
+    fn name(&self) -> Option<N> {
+        self.child.name()
+    }
+    fn render(&self, ctx: RenderContext<N>) -> TextBlock<N> {
+        let width = ctx.bound().width.expect("Padding without width constraint");
+        let height = ctx
+            .bound()
+            .height
+            .expect("Padding without height constraint");
+        let background = ctx.clip_lines(self.class, vec![]);
+        let cols = width.saturating_sub(self.left + self.right);
+        let rows = height.saturating_sub(self.top + self.bottom);
+        if cols == 0 || rows == 0 {
+            return background;
+        }
+        let child = ctx.render_sized(RenderBound::new(Some(cols), Some(rows)), self.child);
+        background.overlay(child, Pos::new(self.left, self.top))
+    }
+    fn size_hint(&self, bound: RenderBound) -> SizeHint {
+        let (cols, rows) = (self.left + self.right, self.top + self.bottom);
+        let inner = RenderBound::new(
+            bound.width.map(|w| w.saturating_sub(cols)),
+            bound.height.map(|h| h.saturating_sub(rows)),
+        );
+        let hint = self.child.size_hint(inner);
+        let grow = |r: SizeRange, by: usize| {
+            SizeRange::new(r.min + by, r.preferred + by, r.max.map(|m| m + by))
+        };
+        SizeHint::new(grow(hint.width, cols), grow(hint.height, rows))
+    }
+    fn widget_type(&self) -> &'static str {
+        "Padding"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Padding;
+    use executor::Event;
+    use test_util::{click, event_context};
+    use widget::SimpleInput;
+    use {AppEvent, Pos, RenderBound, RenderContext, Widget};
+
+    #[test]
+    fn insets_child() {
+        let input = SimpleInput::new(1);
+        let padding = Padding::new(&input, 1, 2, 1, 3).with_class("Inset");
+        assert_eq!(
+            padding.size_hint(RenderBound::new(None, None)).height.min,
+            3
+        );
+        let bound = RenderBound::new(Some(10), Some(3));
+        let block = padding.render(RenderContext::from_widget(bound, &padding));
+        assert_eq!(block.lines[0].segments[0].id.class, "Inset");
+        let mut classes: Vec<&str> = block.lines[1].segments.iter().map(|s| s.id.class).collect();
+        classes.dedup();
+        assert_eq!(classes, vec!["Inset", "Buffer", "Inset"]);
+        assert_eq!(block.get_cursor(1), Some(Pos::new(3, 1)));
+
+        let (ctx, receiver) = event_context();
+        block.handle_mouse(&ctx, click(2, 1));
+        block.handle_mouse(&ctx, click(8, 1));
+        block.handle_mouse(&ctx, click(5, 0));
+        assert!(receiver.try_iter().next().is_none());
+        block.handle_mouse(&ctx, click(3, 1));
+        let events: Vec<Event<u8>> = receiver.try_iter().collect();
+        assert_eq!(events, vec![Event::App(AppEvent::SetFocus(1))]);
+    }
+}
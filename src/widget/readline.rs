@@ -1,7 +1,7 @@
-use input::Key;
+use input::{KeyCode, KeyEvent, Modifiers};
 use {
-    shared, AppEvent, FullGrowthPolicy, Name, Pos, RenderContext, Shared, ShouldPropagate,
-    TextBlock, Widget,
+    shared, AppEvent, CursorShape, CursorStyle, Name, Pos, RenderBound, RenderContext, Shared,
+    ShouldPropagate, SizeHint, TextBlock, Widget,
 };
 
 use std::fmt;
@@ -108,14 +108,14 @@ impl<N: Name> ReadlineInner<N> {
         );
     }
 
-    fn handle_key(&mut self, key: Key) -> ShouldPropagate {
+    fn handle_key(&mut self, key: KeyEvent) -> ShouldPropagate {
         use ShouldPropagate::*;
-        match key {
-            Key::Char('\n') => {
+        match key.code {
+            KeyCode::Enter if key.modifiers.is_empty() => {
                 self.submit();
                 Stop
             }
-            Key::Esc => Continue,
+            KeyCode::Esc => Continue,
             _ => {
                 self.process_key(key);
                 Stop
@@ -133,10 +133,20 @@ impl<N: Name> ReadlineInner<N> {
         self.listeners.retain(|l| l(&event));
     }
 
+    fn cursor_style(&self) -> CursorStyle {
+        if self.input_state.is_command_mode() {
+            CursorStyle::steady(CursorShape::Block)
+        } else if self.input_state.is_replace_mode() {
+            CursorStyle::steady(CursorShape::Underline)
+        } else {
+            CursorStyle::steady(CursorShape::Bar)
+        }
+    }
+
     fn _set_line(&mut self, text: &str) {
         self.state.set_line(text);
     }
-    fn process_key(&mut self, key: Key) {
+    fn process_key(&mut self, key: KeyEvent) {
         self.process_keypress(key_to_keypress(key))
     }
     pub fn add_listener(&mut self, l: Box<Fn(&ReadlineEvent<N>) -> bool>) {
@@ -145,26 +155,47 @@ impl<N: Name> ReadlineInner<N> {
 }
 
 // text-ui integration to be broken out later
-fn key_to_keypress(key: Key) -> KeyPress {
-    match key {
-        Key::Backspace => KeyPress::Backspace,
-        Key::Left => KeyPress::Left,
-        Key::Right => KeyPress::Right,
-        Key::Up => KeyPress::Up,
-        Key::Down => KeyPress::Down,
-        Key::Home => KeyPress::Home,
-        Key::End => KeyPress::End,
-        Key::PageUp => KeyPress::PageUp,
-        Key::PageDown => KeyPress::PageDown,
-        Key::Delete => KeyPress::Delete,
-        Key::Insert => KeyPress::Insert,
-        Key::F(u8) => KeyPress::F(u8),
-        Key::Char(char) => KeyPress::Char(char),
-        Key::Alt(char) => KeyPress::Meta(char.to_ascii_uppercase()),
-        Key::Ctrl(char) => KeyPress::Ctrl(char.to_ascii_uppercase()),
-        Key::Null => KeyPress::Null,
-        Key::Esc => KeyPress::Esc,
-        _ => KeyPress::UnknownEscSeq,
+fn key_to_keypress(key: KeyEvent) -> KeyPress {
+    let mods = key.modifiers;
+    match key.code {
+        KeyCode::Char(char) if mods.contains(Modifiers::ALT) => {
+            KeyPress::Meta(char.to_ascii_uppercase())
+        }
+        KeyCode::Char(char) if mods.contains(Modifiers::CTRL) => {
+            KeyPress::Ctrl(char.to_ascii_uppercase())
+        }
+        KeyCode::Char(char) => KeyPress::Char(char),
+        code if mods == Modifiers::CTRL => match code {
+            KeyCode::Left => KeyPress::ControlLeft,
+            KeyCode::Right => KeyPress::ControlRight,
+            KeyCode::Up => KeyPress::ControlUp,
+            KeyCode::Down => KeyPress::ControlDown,
+            _ => KeyPress::UnknownEscSeq,
+        },
+        code if mods == Modifiers::SHIFT => match code {
+            KeyCode::Left => KeyPress::ShiftLeft,
+            KeyCode::Right => KeyPress::ShiftRight,
+            KeyCode::Up => KeyPress::ShiftUp,
+            KeyCode::Down => KeyPress::ShiftDown,
+            _ => KeyPress::UnknownEscSeq,
+        },
+        _ if !mods.is_empty() => KeyPress::UnknownEscSeq,
+        KeyCode::Enter => KeyPress::Enter,
+        KeyCode::Tab => KeyPress::Tab,
+        KeyCode::Backspace => KeyPress::Backspace,
+        KeyCode::Left => KeyPress::Left,
+        KeyCode::Right => KeyPress::Right,
+        KeyCode::Up => KeyPress::Up,
+        KeyCode::Down => KeyPress::Down,
+        KeyCode::Home => KeyPress::Home,
+        KeyCode::End => KeyPress::End,
+        KeyCode::PageUp => KeyPress::PageUp,
+        KeyCode::PageDown => KeyPress::PageDown,
+        KeyCode::Delete => KeyPress::Delete,
+        KeyCode::Insert => KeyPress::Insert,
+        KeyCode::F(u8) => KeyPress::F(u8),
+        KeyCode::Null => KeyPress::Null,
+        KeyCode::Esc => KeyPress::Esc,
     }
 }
 
@@ -178,11 +209,11 @@ impl<N: Name + 'static> Widget<N> for Readline<N> {
             .width
             .expect("Rendering Readline without width constraint");
         let inner = self.inner.clone();
-        let (lines, focus, name) = {
+        let (lines, focus, cursor_style, name) = {
             let inner = inner.read().unwrap();
             let lines = inner.state.render_width(width);
             let focus = Pos::new(inner.state.cursor.col, inner.state.cursor.row);
-            (lines, focus, inner.name)
+            (lines, focus, inner.cursor_style(), inner.name)
         };
         ctx.clip_lines("Buffer", lines)
             .add_key_handler(
@@ -190,12 +221,12 @@ impl<N: Name + 'static> Widget<N> for Readline<N> {
                 Box::new(move |_ctx, k| inner.write().unwrap().handle_key(k)),
             ).add_mouse_handler(
                 Some(name),
-                Box::new(move |ctx, _pos, _m| {
+                Box::new(move |ctx, _m| {
                     let _ = ctx.send_event(AppEvent::SetFocus(name));
                     // update index
                     ShouldPropagate::Stop
                 }),
-            ).add_cursor(name, focus)
+            ).add_styled_cursor(name, focus, cursor_style)
         /*
         let inner = self.inner.clone();
         let inner2 = inner.clone();
@@ -209,16 +240,21 @@ impl<N: Name + 'static> Widget<N> for Readline<N> {
                 Box::new(move |_ctx, k| inner.write().unwrap().handle_key(k)),
             ).add_mouse_handler(
                 Some(name),
-                Box::new(move |ctx, pos, _m| {
+                Box::new(move |ctx, m| {
                     let _ = ctx.send_event(AppEvent::SetFocus(name));
-                    inner2.write().unwrap().set_index(pos.col);
+                    inner2.write().unwrap().set_index(m.pos.col);
                     ShouldPropagate::Stop
                 }),
             ).add_cursor(name, Pos::new(index, 0))
             */
     }
-    fn growth_policy(&self) -> FullGrowthPolicy {
-        FullGrowthPolicy::fixed_height()
+    // As tall as the wrapped buffer
+    fn size_hint(&self, bound: RenderBound) -> SizeHint {
+        let rows = match bound.width {
+            Some(width) => self.inner.read().unwrap().state.render_width(width).len(),
+            None => 1,
+        };
+        SizeHint::fixed_height(rows)
     }
     fn widget_type(&self) -> &'static str {
         "Readline"
@@ -145,7 +145,7 @@ impl History {
         let file = try!(File::open(&path));
         let rdr = BufReader::new(file);
         for line in rdr.lines() {
-            self.add(try!(line).as_ref()); // TODO truncate to MAX_LINE
+            self.add::<&str>(try!(line).as_ref()); // TODO truncate to MAX_LINE
         }
         Ok(())
     }
@@ -292,6 +292,16 @@ impl InputState {
         self.mode == EditMode::Emacs
     }
 
+    /// Vi only, whether keys are commands rather than text.
+    pub fn is_command_mode(&self) -> bool {
+        self.mode == EditMode::Vi && self.input_mode == InputMode::Command
+    }
+
+    /// Vi only, whether typed text overwrites the line.
+    pub fn is_replace_mode(&self) -> bool {
+        self.mode == EditMode::Vi && self.input_mode == InputMode::Replace
+    }
+
     /// Parse user input into one command
     /// `single_esc_abort` is used in emacs mode on unix platform when a single
     /// esc key is expected to abort current action.
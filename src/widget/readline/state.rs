@@ -11,6 +11,7 @@ use super::keymap::{InputState, Refresher};
 use super::line_buffer::{LineBuffer, WordAction, MAX_LINE};
 use super::undo::Changeset;
 use super::Offset as Position;
+use ir::{grapheme_width, split_line_graphemes};
 
 /// Represent the state during line editing.
 /// Implement rendering.
@@ -54,23 +55,32 @@ impl State {
 
     pub fn refresh(&mut self) {
         let focuspos = self.line.pos();
+        let width = self.width;
         let mut focus = Position::new(0, 0);
         let mut idx = 0;
-        let lines = self.render_width(self.width);
-        self.rows = lines.len();
-        'outer: for (row, line) in lines.into_iter().enumerate() {
-            if idx == focuspos {
-                focus = Position::new(0, row);
-                break 'outer;
-            }
-            for (col, _ch) in UnicodeSegmentation::graphemes(line.as_str(), true).enumerate() {
-                idx += 1;
+        let mut row = 0;
+        self.rows = self.render_width(width).len();
+        'outer: for line in self.line.as_str().split('\n') {
+            let mut col = 0;
+            for grapheme in UnicodeSegmentation::graphemes(line, true) {
+                let w = grapheme_width(grapheme);
+                if col + w > width {
+                    row += 1;
+                    col = 0;
+                }
                 if idx == focuspos {
-                    focus = Position::new(col + 1, row);
+                    focus = Position::new(col, row);
                     break 'outer;
                 }
+                idx += grapheme.len();
+                col += w;
+            }
+            if idx == focuspos {
+                focus = Position::new(col, row);
+                break 'outer;
             }
             idx += 1;
+            row += 1;
         }
         self.cursor = focus;
     }
@@ -81,11 +91,7 @@ impl State {
             .as_str()
             .split('\n')
             .flat_map(|l| {
-                let letters: Vec<&str> = UnicodeSegmentation::graphemes(l, true).collect();
-                let mut split_lines = letters
-                    .chunks(width)
-                    .map(|ls| ls.concat())
-                    .collect::<Vec<String>>();
+                let mut split_lines = split_line_graphemes(l, width);
                 if split_lines.len() == 0 {
                     split_lines.push("".to_string());
                 }
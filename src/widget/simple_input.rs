@@ -1,7 +1,9 @@
-use input::Key;
+use input::{KeyCode, KeyEvent, Modifiers};
+use ir::{grapheme_width, text_width};
 use std::fmt;
+use unicode_segmentation::UnicodeSegmentation;
 use {
-    shared, AppEvent, FullGrowthPolicy, Name, Pos, RenderContext, Shared, ShouldPropagate,
+    shared, AppEvent, Name, Pos, RenderBound, RenderContext, Shared, ShouldPropagate, SizeHint,
     TextBlock, Widget,
 };
 
@@ -48,24 +50,36 @@ where
         let event = SimpleInputEvent::Submitted { name, line };
         self.listeners.retain(|l| l(&event));
     }
-    pub fn handle_key(&mut self, key: Key) -> ShouldPropagate {
+    pub fn handle_key(&mut self, key: KeyEvent) -> ShouldPropagate {
         use ShouldPropagate::*;
-        match key {
-            Key::Char('\n') => {
+        match key.code {
+            KeyCode::Enter => {
                 self.submit();
                 Stop
             }
-            Key::Char(ch) => {
+            KeyCode::Char(ch) if !key.modifiers.intersects(Modifiers::CTRL | Modifiers::ALT) => {
                 self.line.insert(self.index, ch);
-                self.index += 1;
+                self.index += ch.len_utf8();
                 Stop
             }
-            Key::Esc => Continue,
+            KeyCode::Esc => Continue,
             _ => Continue,
         }
     }
-    pub fn set_index(&mut self, new_idx: usize) {
-        self.index = std::cmp::min(new_idx, self.line.len());
+    // Moves to the grapheme under the given display column
+    pub fn set_index(&mut self, col: usize) {
+        let mut width = 0;
+        self.index = self.line.len();
+        for (idx, grapheme) in UnicodeSegmentation::grapheme_indices(self.line.as_str(), true) {
+            width += grapheme_width(grapheme);
+            if width > col {
+                self.index = idx;
+                break;
+            }
+        }
+    }
+    pub fn cursor_col(&self) -> usize {
+        text_width(&self.line[..self.index])
     }
 }
 
@@ -124,7 +138,7 @@ where
         let inner2 = inner.clone();
         let name = inner.read().unwrap().name;
         let line = inner.read().unwrap().line.to_string();
-        let index = inner.read().unwrap().index;
+        let index = inner.read().unwrap().cursor_col();
         ctx.with_bound(ctx.bound().constrain_height(1))
             .clip_lines("Buffer", vec![line])
             .add_key_handler(
@@ -132,17 +146,64 @@ where
                 Box::new(move |_ctx, k| inner.write().unwrap().handle_key(k)),
             ).add_mouse_handler(
                 Some(name),
-                Box::new(move |ctx, pos, _m| {
+                Box::new(move |ctx, m| {
                     let _ = ctx.send_event(AppEvent::SetFocus(name));
-                    inner2.write().unwrap().set_index(pos.col);
+                    inner2.write().unwrap().set_index(m.pos.col);
                     ShouldPropagate::Stop
                 }),
             ).add_cursor(name, Pos::new(index, 0))
     }
-    fn growth_policy(&self) -> FullGrowthPolicy {
-        FullGrowthPolicy::fixed_height()
+    fn size_hint(&self, _bound: RenderBound) -> SizeHint {
+        SizeHint::fixed_height(1)
     }
     fn widget_type(&self) -> &'static str {
         "SimpleInput"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::SimpleInput;
+    use {App, Color, ContentID, RenderBound, RenderContext, Style, TextBlock, Widget};
+
+    // Styles the buffer only while it has focus, as App::style or a theme would
+    #[derive(Debug)]
+    struct FocusApp {
+        input: SimpleInput<u8>,
+    }
+
+    impl Widget<u8> for FocusApp {
+        fn render(&self, ctx: RenderContext<u8>) -> TextBlock<u8> {
+            ctx.render_sized(ctx.bound(), &self.input)
+        }
+        fn name(&self) -> Option<u8> {
+            None
+        }
+        fn widget_type(&self) -> &'static str {
+            "FocusApp"
+        }
+    }
+
+    impl App<u8> for FocusApp {
+        fn style(&self, cid: ContentID<u8>) -> Style {
+            match cid.as_tuple() {
+                (_, "SimpleInput", "Buffer") if cid.focused => Style::new().bg(Color::Blue),
+                _ => Style::new(),
+            }
+        }
+    }
+
+    #[test]
+    fn styled_by_focus() {
+        let app = FocusApp {
+            input: SimpleInput::new(1),
+        };
+        let bound = RenderBound::new(Some(4), Some(1));
+        let block = app.render(RenderContext::from_widget(bound, &app));
+        assert_eq!(block.lines[0].segments[0].id.class, "Buffer");
+        let focused = block.render_frame(&app, Some(1));
+        assert_eq!(focused.image[0][0].0, Style::new().bg(Color::Blue));
+        let blurred = block.render_frame(&app, Some(2));
+        assert_eq!(blurred.image[0][0].0, Style::new());
+    }
+}
@@ -0,0 +1,214 @@
+use widget::align::fit;
+use {Name, Pos, RenderBound, RenderContext, SizeHint, SizeRange, TextBlock, Widget};
+
+// Where a layer sits within the stack. Anchored layers are rendered at their preferred size,
+// while Fill layers take the whole bound.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Anchor {
+    #[default]
+    Fill,
+    Center,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    At(Pos),
+}
+
+#[derive(Debug)]
+struct Layer<'a, N>
+where
+    N: 'a + Name,
+{
+    widget: &'a dyn Widget<N>,
+    anchor: Anchor,
+    modal: bool,
+}
+
+// Paints layers over each other, back to front, for dialogs and floating panels. Once there is
+// a modal layer, the topmost one is all that takes key input and mouse hits, and everything
+// under it has its text given the "Dim" class so it can be styled as inactive.
+#[derive(Debug)]
+pub struct Stack<'a, N>
+where
+    N: 'a + Name,
+{
+    layers: Vec<Layer<'a, N>>,
+    dim_class: &'static str,
+}
+
+impl<'a, N> Default for Stack<'a, N>
+where
+    N: 'a + Name,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, N> Stack<'a, N>
+where
+    N: 'a + Name,
+{
+    pub fn new() -> Self {
+        let layers = vec![];
+        let dim_class = "Dim";
+        Self { layers, dim_class }
+    }
+    pub fn layer(self, widget: &'a dyn Widget<N>, anchor: Anchor) -> Self {
+        self.push(widget, anchor, false)
+    }
+    pub fn modal(self, widget: &'a dyn Widget<N>, anchor: Anchor) -> Self {
+        self.push(widget, anchor, true)
+    }
+    pub fn with_dim_class(mut self, class: &'static str) -> Self {
+        self.dim_class = class;
+        self
+    }
+    fn push(mut self, widget: &'a dyn Widget<N>, anchor: Anchor, modal: bool) -> Self {
+        self.layers.push(Layer {
+            widget,
+            anchor,
+            modal,
+        });
+        self
+    }
+}
+
+// Covers all the ranges, with no limit on growing
+pub(crate) fn largest<I: Iterator<Item = SizeRange>>(ranges: I) -> SizeRange {
+    ranges.fold(SizeRange::default(), |a, b| {
+        SizeRange::new(a.min.max(b.min), a.preferred.max(b.preferred), None)
+    })
+}
+
+impl<'a, N> Widget<N> for Stack<'a, N>
+where
+    N: 'a + Name,
+{
+    fn name(&self) -> Option<N> {
+        None
+    }
+    fn render(&self, ctx: RenderContext<N>) -> TextBlock<N> {
+        let width = ctx.bound().width.expect("Stack without width constraint");
+        let height = ctx.bound().height.expect("Stack without height constraint");
+        let top_modal = self.layers.iter().rposition(|layer| layer.modal);
+        let mut block = ctx.clip_lines("Blank", vec![]);
+        for (idx, layer) in self.layers.iter().enumerate() {
+            if Some(idx) == top_modal {
+                block = block.without_input().with_class(self.dim_class);
+            }
+            // Anchored at a position, a layer only has the room between it and the far edges
+            let (avail_cols, avail_rows) = match layer.anchor {
+                Anchor::At(pos) => (
+                    width.saturating_sub(pos.col),
+                    height.saturating_sub(pos.row),
+                ),
+                _ => (width, height),
+            };
+            let (cols, rows) = match layer.anchor {
+                Anchor::Fill => (width, height),
+                _ => {
+                    let hint = layer
+                        .widget
+                        .size_hint(RenderBound::new(None, Some(avail_rows)));
+                    let cols = fit(hint.width, avail_cols);
+                    let hint = layer.widget.size_hint(RenderBound::new(Some(cols), None));
+                    (cols, fit(hint.height, avail_rows))
+                }
+            };
+            if cols == 0 || rows == 0 {
+                continue;
+            }
+            let pos = match layer.anchor {
+                Anchor::Fill | Anchor::TopLeft => Pos::new(0, 0),
+                Anchor::Center => Pos::new((width - cols) / 2, (height - rows) / 2),
+                Anchor::TopRight => Pos::new(width - cols, 0),
+                Anchor::BottomLeft => Pos::new(0, height - rows),
+                Anchor::BottomRight => Pos::new(width - cols, height - rows),
+                Anchor::At(pos) => pos,
+            };
+            let mut child =
+                ctx.render_sized(RenderBound::new(Some(cols), Some(rows)), layer.widget);
+            if top_modal.map(|top| idx > top) == Some(true) {
+                child = child.without_input();
+            }
+            block = block.overlay(child, pos);
+        }
+        block
+    }
+    // Big enough for the largest Fill layer, since anchored layers are cropped to fit
+    fn size_hint(&self, bound: RenderBound) -> SizeHint {
+        let hints: Vec<SizeHint> = self
+            .layers
+            .iter()
+            .filter(|layer| layer.anchor == Anchor::Fill)
+            .map(|layer| layer.widget.size_hint(bound))
+            .collect();
+        SizeHint::new(
+            largest(hints.iter().map(|h| h.width)),
+            largest(hints.iter().map(|h| h.height)),
+        )
+    }
+    fn widget_type(&self) -> &'static str {
+        "Stack"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Anchor, Stack};
+    use executor::Event;
+    use test_util::{click, event_context};
+    use widget::{Constraint, Linear, SimpleInput};
+    use {AppEvent, Pos, RenderBound, RenderContext, Widget};
+
+    #[test]
+    fn modal_takes_input() {
+        let (main, dialog, panel) = (
+            SimpleInput::new(1),
+            SimpleInput::new(2),
+            SimpleInput::new(3),
+        );
+        let sized: Linear<u8> = Linear::hbox(vec![&dialog]).constrain(0, Constraint::fixed(4));
+        let small: Linear<u8> = Linear::hbox(vec![&panel]).constrain(0, Constraint::fixed(2));
+        let base: Linear<u8> = Linear::vbox(vec![&main]);
+        let bound = RenderBound::new(Some(10), Some(3));
+
+        let stack = Stack::new()
+            .layer(&base, Anchor::Fill)
+            .layer(&small, Anchor::BottomRight);
+        let block = stack.render(RenderContext::from_widget(bound, &stack));
+        assert_eq!(block.focus_order(), vec![1, 3]);
+        assert_eq!(block.get_cursor(1), Some(Pos::new(0, 0)));
+        assert_eq!(block.get_cursor(3), Some(Pos::new(8, 2)));
+
+        let stack = Stack::new()
+            .layer(&base, Anchor::Fill)
+            .modal(&sized, Anchor::Center)
+            .layer(&small, Anchor::At(Pos::new(9, 0)));
+        let block = stack.render(RenderContext::from_widget(bound, &stack));
+        assert_eq!(block.focus_order(), vec![2]);
+        // Focus left under the modal has somewhere to go
+        assert!(block.is_covered(&1));
+        assert!(block.is_covered(&3));
+        assert_eq!(block.next_focus(&1), Some(2));
+        assert_eq!(block.get_cursor(1), None);
+        assert_eq!(block.get_cursor(2), Some(Pos::new(3, 1)));
+        assert_eq!(block.get_cursor(3), None);
+        assert_eq!(block.lines[0].segments[0].id.name, Some(1));
+        assert_eq!(block.lines[0].segments[0].id.class, "Dim");
+        assert_eq!(block.lines[1].segments[0].id.class, "Dim");
+        assert_eq!(block.lines[0].segments.last().unwrap().id.class, "Buffer");
+
+        let (ctx, receiver) = event_context();
+        block.handle_mouse(&ctx, click(0, 0));
+        block.handle_mouse(&ctx, click(8, 1));
+        // Layers above the modal are still drawn, but take nothing either
+        block.handle_mouse(&ctx, click(9, 0));
+        assert!(receiver.try_iter().next().is_none());
+        block.handle_mouse(&ctx, click(4, 1));
+        let events: Vec<Event<u8>> = receiver.try_iter().collect();
+        assert_eq!(events, vec![Event::App(AppEvent::SetFocus(2))]);
+    }
+}
@@ -0,0 +1,460 @@
+use std::fmt;
+use std::mem;
+
+use input::{KeyCode, KeyEvent, Modifiers, MouseAction, MouseButton};
+use ir::{sanitize, text_width};
+use widget::stack::largest;
+use {
+    shared, AppEvent, Name, Pos, Rect, RenderBound, RenderContext, RichLine, Shared,
+    ShouldPropagate, Size, SizeHint, SizeRange, TextBlock, Widget,
+};
+
+pub enum TabsEvent<'a> {
+    Selected { index: usize, title: &'a str },
+    Closed { index: usize, title: &'a str },
+}
+
+pub type TabsListener = Box<dyn Fn(&TabsEvent) -> bool>;
+
+// What happened to the tabs, kept until the lock is released to tell the listeners
+enum Change {
+    Selected(usize, String),
+    Closed(usize, String),
+}
+
+impl Change {
+    fn event(&self) -> TabsEvent<'_> {
+        match self {
+            Change::Selected(index, title) => TabsEvent::Selected {
+                index: *index,
+                title,
+            },
+            Change::Closed(index, title) => TabsEvent::Closed {
+                index: *index,
+                title,
+            },
+        }
+    }
+}
+
+struct Tab {
+    title: String,
+    activity: bool,
+}
+
+struct TabsInner {
+    tabs: Vec<Tab>,
+    selected: usize,
+    listeners: Vec<TabsListener>,
+}
+
+impl TabsInner {
+    fn select(&mut self, index: usize) -> Vec<Change> {
+        if index >= self.tabs.len() || index == self.selected {
+            return vec![];
+        }
+        self.selected = index;
+        self.tabs[index].activity = false;
+        let title = self.tabs[index].title.clone();
+        vec![Change::Selected(index, title)]
+    }
+    // Steps through the tabs, wrapping around at either end
+    fn step(&mut self, forward: bool) -> Vec<Change> {
+        let count = self.tabs.len();
+        if count == 0 {
+            return vec![];
+        }
+        let index = if forward {
+            (self.selected + 1) % count
+        } else {
+            (self.selected + count - 1) % count
+        };
+        self.select(index)
+    }
+    // Closing the selected tab selects the one that takes its place
+    fn close(&mut self, index: usize) -> Vec<Change> {
+        if index >= self.tabs.len() {
+            return vec![];
+        }
+        let was_selected = index == self.selected;
+        let tab = self.tabs.remove(index);
+        if self.selected > index || self.selected == self.tabs.len() {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        let mut changes = vec![Change::Closed(index, tab.title)];
+        if let Some(tab) = self.tabs.get_mut(self.selected) {
+            tab.activity = false;
+            if was_selected {
+                changes.push(Change::Selected(self.selected, tab.title.clone()));
+            }
+        }
+        changes
+    }
+    fn mark_activity(&mut self, index: usize) -> Vec<Change> {
+        if index != self.selected {
+            if let Some(tab) = self.tabs.get_mut(index) {
+                tab.activity = true;
+            }
+        }
+        vec![]
+    }
+}
+
+// Makes a change under the lock, then tells the listeners once it's released, so they can look
+// at the tabs. Listeners added meanwhile are kept after the others.
+fn change<F>(inner: &Shared<TabsInner>, f: F)
+where
+    F: FnOnce(&mut TabsInner) -> Vec<Change>,
+{
+    let changes = f(&mut inner.write().unwrap());
+    if changes.is_empty() {
+        return;
+    }
+    let mut listeners = mem::take(&mut inner.write().unwrap().listeners);
+    for change in &changes {
+        let event = change.event();
+        listeners.retain(|l| l(&event));
+    }
+    let mut inner = inner.write().unwrap();
+    listeners.append(&mut inner.listeners);
+    inner.listeners = listeners;
+}
+
+// The titles, selection and activity markers of a set of tabs, kept by the app between renders
+// while the Tabs showing them are built anew each time. Children can't be watched from here, so
+// whatever updates a background tab marks its activity.
+#[derive(Clone)]
+pub struct TabsState {
+    inner: Shared<TabsInner>,
+}
+
+impl Default for TabsState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TabsState {
+    pub fn new() -> Self {
+        let inner = shared(TabsInner {
+            tabs: vec![],
+            selected: 0,
+            listeners: vec![],
+        });
+        Self { inner }
+    }
+    pub fn add_tab<S: Into<String>>(self, title: S) -> Self {
+        self.inner.write().unwrap().tabs.push(Tab {
+            title: title.into(),
+            activity: false,
+        });
+        self
+    }
+    pub fn add_listener(self, l: TabsListener) -> Self {
+        self.inner.write().unwrap().listeners.push(l);
+        self
+    }
+    pub fn selected(&self) -> usize {
+        self.inner.read().unwrap().selected
+    }
+    pub fn select(&self, index: usize) {
+        change(&self.inner, |inner| inner.select(index))
+    }
+    // Closes the tab, leaving the app to drop its child from the Tabs it builds
+    pub fn close(&self, index: usize) {
+        change(&self.inner, |inner| inner.close(index))
+    }
+    // Marks a background tab as having something new, until it's selected
+    pub fn mark_activity(&self, index: usize) {
+        change(&self.inner, |inner| inner.mark_activity(index))
+    }
+}
+
+impl fmt::Debug for TabsState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let inner = self.inner.read().unwrap();
+        let titles: Vec<&str> = inner.tabs.iter().map(|t| t.title.as_str()).collect();
+        f.debug_struct("TabsState")
+            .field("titles", &titles)
+            .field("selected", &inner.selected)
+            .finish()
+    }
+}
+
+// Shows one of several children at a time, under a strip of their titles. The children go in
+// the same order as the state's tabs. The strip has the "TabStrip" class, with titles in "Tab"
+// or "SelectedTab", and the "Activity" marker and "Close" buttons in their own classes. Clicking
+// a title selects its tab, and so do the next and previous keys when the tabs or one of their
+// children has focus.
+#[derive(Debug)]
+pub struct Tabs<'a, N>
+where
+    N: 'a + Name,
+{
+    name: Option<N>,
+    state: &'a TabsState,
+    children: Vec<&'a dyn Widget<N>>,
+    next_keys: Vec<KeyEvent>,
+    prev_keys: Vec<KeyEvent>,
+    closable: bool,
+}
+
+impl<'a, N> Tabs<'a, N>
+where
+    N: 'a + Name,
+{
+    pub fn new(name: Option<N>, state: &'a TabsState) -> Self {
+        let children = vec![];
+        let next_keys = vec![KeyEvent::new(KeyCode::PageDown, Modifiers::CTRL)];
+        let prev_keys = vec![KeyEvent::new(KeyCode::PageUp, Modifiers::CTRL)];
+        let closable = false;
+        Self {
+            name,
+            state,
+            children,
+            next_keys,
+            prev_keys,
+            closable,
+        }
+    }
+    pub fn tab(mut self, widget: &'a dyn Widget<N>) -> Self {
+        self.children.push(widget);
+        self
+    }
+    // Replaces the default Ctrl-PageDown and Ctrl-PageUp
+    pub fn with_keys(mut self, next_keys: Vec<KeyEvent>, prev_keys: Vec<KeyEvent>) -> Self {
+        self.next_keys = next_keys;
+        self.prev_keys = prev_keys;
+        self
+    }
+    // Puts a button after each title that closes its tab
+    pub fn with_close_buttons(mut self) -> Self {
+        self.closable = true;
+        self
+    }
+}
+
+// Adds a span to the strip, moving `col` past it
+fn push_span(
+    strip: RichLine,
+    col: &mut usize,
+    tab_width: usize,
+    class: &'static str,
+    text: String,
+) -> RichLine {
+    *col += text_width(&sanitize(&text, *col, tab_width));
+    strip.span(class, text)
+}
+
+impl<'a, N> Widget<N> for Tabs<'a, N>
+where
+    N: 'static + Name,
+{
+    fn name(&self) -> Option<N> {
+        self.name
+    }
+    fn render(&self, ctx: RenderContext<N>) -> TextBlock<N> {
+        let width = ctx.bound().width.expect("Tabs without width constraint");
+        let height = ctx.bound().height.expect("Tabs without height constraint");
+        if height == 0 {
+            return ctx.clip_lines("TabStrip", vec![]);
+        }
+        let body_bound = RenderBound::new(Some(width), Some(height - 1));
+        let selected = self.state.selected();
+        let body = match self.children.get(selected) {
+            Some(child) => ctx.render_sized(body_bound, *child),
+            None => ctx.with_bound(body_bound).clip_lines("Blank", vec![]),
+        };
+
+        let inner = self.state.inner.read().unwrap();
+        // Each title's columns, and the column of its close button
+        let tab_width = ctx.tab_width();
+        let mut hits: Vec<(usize, usize, Option<usize>)> = vec![];
+        let mut strip = RichLine::new();
+        let mut col = 0;
+        for (idx, tab) in inner.tabs.iter().enumerate() {
+            let class = if idx == selected {
+                "SelectedTab"
+            } else {
+                "Tab"
+            };
+            let start = col;
+            strip = push_span(strip, &mut col, tab_width, class, format!(" {}", tab.title));
+            if tab.activity {
+                strip = push_span(strip, &mut col, tab_width, "Activity", "*".to_owned());
+            }
+            strip = push_span(strip, &mut col, tab_width, class, " ".to_owned());
+            let close = if self.closable {
+                let close = col;
+                strip = push_span(strip, &mut col, tab_width, "Close", "×".to_owned());
+                Some(close)
+            } else {
+                None
+            };
+            hits.push((start, col, close));
+        }
+        drop(inner);
+
+        let (inner, inner2) = (self.state.inner.clone(), self.state.inner.clone());
+        let (next_keys, prev_keys) = (self.next_keys.clone(), self.prev_keys.clone());
+        let name = self.name;
+        ctx.with_bound(RenderBound::new(Some(width), Some(1)))
+            .clip_rich("TabStrip", vec![strip])
+            .vconcat(body)
+            .add_key_handler(
+                self.name,
+                Box::new(move |_ctx, k| {
+                    if next_keys.contains(&k) {
+                        change(&inner, |inner| inner.step(true));
+                    } else if prev_keys.contains(&k) {
+                        change(&inner, |inner| inner.step(false));
+                    } else {
+                        return ShouldPropagate::Continue;
+                    }
+                    ShouldPropagate::Stop
+                }),
+            ).add_mouse_handler_rect(
+                self.name,
+                Rect::new(Pos::new(0, 0), Size::new(width, 1)),
+                Box::new(move |ctx, m| {
+                    if m.action != MouseAction::Press(MouseButton::Left) {
+                        return ShouldPropagate::Continue;
+                    }
+                    if let Some(name) = name {
+                        let _ = ctx.send_event(AppEvent::SetFocus(name));
+                    }
+                    let col = m.pos.col;
+                    let hit = hits
+                        .iter()
+                        .position(|&(start, end, _)| start <= col && col < end);
+                    if let Some(idx) = hit {
+                        if hits[idx].2 == Some(col) {
+                            change(&inner2, |inner| inner.close(idx));
+                        } else {
+                            change(&inner2, |inner| inner.select(idx));
+                        }
+                    }
+                    ShouldPropagate::Stop
+                }),
+            )
+    }
+    // The strip, over room for the largest tab
+    fn size_hint(&self, bound: RenderBound) -> SizeHint {
+        let body_bound = RenderBound::new(bound.width, bound.height.map(|h| h.saturating_sub(1)));
+        let hints: Vec<SizeHint> = self
+            .children
+            .iter()
+            .map(|child| child.size_hint(body_bound))
+            .collect();
+        let height = largest(hints.iter().map(|h| h.height));
+        SizeHint::new(
+            largest(hints.iter().map(|h| h.width)),
+            SizeRange::new(height.min + 1, height.preferred + 1, None),
+        )
+    }
+    fn widget_type(&self) -> &'static str {
+        "Tabs"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Tabs, TabsEvent, TabsState};
+    use executor::Event;
+    use input::{KeyCode, KeyEvent, Modifiers};
+    use test_util::{click, event_context, row_text};
+    use widget::{Log, SimpleInput};
+    use {shared, AppEvent, Pos, RenderBound, RenderContext, Widget};
+
+    #[test]
+    fn switching_and_activity() {
+        let state = TabsState::new().add_tab("in").add_tab("log");
+        let input = SimpleInput::new(1);
+        let mut log = Log::new(None);
+        log.log_msg("second");
+        let bound = RenderBound::new(Some(16), Some(2));
+        let render = || {
+            let tabs = Tabs::new(Some(9), &state)
+                .tab(&input)
+                .tab(&log)
+                .with_close_buttons();
+            tabs.render(RenderContext::from_widget(bound, &tabs))
+        };
+        let block = render();
+        assert_eq!(row_text(&block, 0), " in × log ×     ");
+        assert_eq!(block.get_cursor(1), Some(Pos::new(0, 1)));
+        let classes: Vec<&str> = block.lines[0].segments.iter().map(|s| s.id.class).collect();
+        assert_eq!(
+            classes,
+            vec![
+                "SelectedTab",
+                "SelectedTab",
+                "Close",
+                "Tab",
+                "Tab",
+                "Close",
+                "TabStrip"
+            ]
+        );
+
+        // Keys reach the tabs whether a child or they have focus
+        let (ctx, receiver) = event_context();
+        block.handle_key(&ctx, &1, KeyEvent::new(KeyCode::PageDown, Modifiers::CTRL));
+        assert_eq!(state.selected(), 1);
+        let block = render();
+        assert_eq!(row_text(&block, 1), "second          ");
+        block.handle_key(&ctx, &9, KeyEvent::new(KeyCode::PageUp, Modifiers::CTRL));
+        assert_eq!(state.selected(), 0);
+
+        // Only a tab in the background shows activity
+        state.mark_activity(0);
+        state.mark_activity(1);
+        let block = render();
+        assert_eq!(row_text(&block, 0), " in × log* ×    ");
+        assert_eq!(block.lines[0].segments[4].id.class, "Activity");
+
+        block.handle_mouse(&ctx, click(6, 0));
+        assert_eq!(state.selected(), 1);
+        let events: Vec<Event<u8>> = receiver.try_iter().collect();
+        assert_eq!(events, vec![Event::App(AppEvent::SetFocus(9))]);
+        let block = render();
+        assert_eq!(row_text(&block, 0), " in × log ×     ");
+
+        block.handle_mouse(&ctx, click(4, 0));
+        assert_eq!(state.selected(), 0);
+        let tabs = Tabs::new(Some(9), &state).tab(&log).with_close_buttons();
+        let block = tabs.render(RenderContext::from_widget(bound, &tabs));
+        assert_eq!(row_text(&block, 0), " log ×          ");
+        assert_eq!(row_text(&block, 1), "second          ");
+    }
+
+    #[test]
+    fn listeners_can_look() {
+        let state = TabsState::new()
+            .add_tab("one")
+            .add_tab("two")
+            .add_tab("three");
+        let inner = state.inner.clone();
+        let seen = shared(vec![]);
+        let log = seen.clone();
+        let state = state.add_listener(Box::new(move |event| {
+            if let TabsEvent::Selected { index, title } = event {
+                let selected = inner.read().unwrap().selected;
+                log.write()
+                    .unwrap()
+                    .push((*index, title.to_string(), selected));
+            }
+            true
+        }));
+        state.select(1);
+        // The selected tab only moves over
+        state.close(0);
+        // The selected tab is gone, so the next one is selected in its place
+        state.close(0);
+        assert_eq!(
+            *seen.read().unwrap(),
+            vec![(1, "two".to_owned(), 1), (0, "three".to_owned(), 0)]
+        );
+        assert_eq!(state.selected(), 0);
+    }
+}